@@ -0,0 +1,51 @@
+// src/commands/clock.rs
+
+use crate::commands::format::format_duration;
+use crate::commands::log::prepare_new_log;
+use crate::{ClockArgs, ClockCommands};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use dlog::{Log, NewLog, Store};
+
+pub fn handle_clock(args: &ClockArgs, store: &mut Store) -> Result<()> {
+    match &args.command {
+        ClockCommands::In { task, tags, global } => {
+            // 同一时间只为一项任务计时
+            if let Some(active) = store.active_clock()? {
+                let log = store.clock_out(active.id)?;
+                println!("⏹  已结束 \"{}\"，用时 {}", log.content, elapsed(&log));
+            }
+
+            let new_log = NewLog {
+                clock: true,
+                ..prepare_new_log(store, task.clone(), tags.as_deref(), *global)?
+            };
+            let log = store.insert(&new_log)?;
+            println!("⏱  开始计时 \"{}\"", log.content);
+            if let Some(tags) = &log.tags {
+                println!("   标签: {}", tags);
+            }
+        }
+        ClockCommands::Out => {
+            let active = store.active_clock()?.context("当前没有正在计时的任务")?;
+            let log = store.clock_out(active.id)?;
+            println!("⏹  已结束 \"{}\"，用时 {}", log.content, elapsed(&log));
+        }
+        ClockCommands::Status => match store.active_clock()? {
+            Some(log) => println!("⏱  正在计时 \"{}\"，已用时 {}", log.content, elapsed(&log)),
+            None => println!("当前没有正在计时的任务。"),
+        },
+    }
+    Ok(())
+}
+
+/// 计时条目的用时，仍在计时的条目计算到当前时刻
+fn elapsed(log: &Log) -> String {
+    let elapsed = match log.clock_span() {
+        Some((start, end)) => (end.unwrap_or_else(Utc::now) - start)
+            .to_std()
+            .unwrap_or_default(),
+        None => Default::default(),
+    };
+    format_duration(elapsed)
+}
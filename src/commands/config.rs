@@ -0,0 +1,39 @@
+// src/commands/config.rs
+
+use crate::{ConfigArgs, ConfigCommands};
+use anyhow::Result;
+use dlog::config;
+use dlog::Store;
+
+pub fn handle_config(args: &ConfigArgs, store: &mut Store) -> Result<()> {
+    match &args.command {
+        ConfigCommands::List => {
+            for key in config::KEYS {
+                let value = store.config_get(key.name)?;
+                println!(
+                    "{} = {}",
+                    key.name,
+                    value.as_deref().unwrap_or("(未设置)")
+                );
+                println!("    {}", key.description);
+            }
+        }
+        ConfigCommands::Get { key } => match store.config_get(key)? {
+            Some(value) => println!("{}", value),
+            None => println!("配置项 '{}' 未设置。", key),
+        },
+        ConfigCommands::Set { key, value } => {
+            let value = store.config_set(key, value)?;
+            println!("✅ {} = {}", key, value);
+        }
+        ConfigCommands::Unset { key } => {
+            if store.config_unset(key)? {
+                println!("✅ 已删除配置项 '{}'。", key);
+            } else {
+                println!("配置项 '{}' 未设置，无需删除。", key);
+            }
+        }
+    }
+
+    Ok(())
+}
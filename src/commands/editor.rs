@@ -0,0 +1,132 @@
+// src/commands/editor.rs
+
+//! 使用 `$VISUAL` / `$EDITOR` 编辑日志内容，行为与 `git commit` 类似。
+
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::process::Command;
+
+/// 与 `git commit --cleanup=scissors` 相同：这一行及其以下的说明在保存后被删除。
+///
+/// 只删除这一块，内容中以 `#` 开头的行 (如 Markdown 标题) 原样保留。
+/// 剪刀线被删掉时，退而删除与模板中说明行完全相同的行。
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// 在编辑器中打开 `initial`，末尾附加剪刀线与说明行。
+///
+/// # Returns
+/// 删除剪刀线以下的部分与首尾空白后的内容，内容为空时返回 None。
+pub(crate) fn edit(initial: &str, notes: &[String]) -> Result<Option<String>> {
+    let instructions = instructions(notes);
+    let mut template = String::new();
+    if !initial.is_empty() {
+        template.push_str(initial);
+        template.push('\n');
+    }
+    template.push('\n');
+    template.push_str(SCISSORS);
+    template.push('\n');
+    for line in &instructions {
+        template.push_str(line);
+        template.push('\n');
+    }
+
+    let file = tempfile::Builder::new()
+        .prefix("dlog-")
+        .suffix(".md")
+        .tempfile()
+        .context("无法创建临时文件")?;
+    fs::write(file.path(), template).context("无法写入临时文件")?;
+    // 关闭文件句柄，部分平台上编辑器无法写入仍被打开的文件
+    let path = file.into_temp_path();
+
+    // 编辑器命令可以带参数，如 `code --wait`
+    let editor = editor_command();
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().context("编辑器命令为空")?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(&path)
+        .status()
+        .with_context(|| format!("无法启动编辑器 '{}'", editor))?;
+    if !status.success() {
+        bail!("编辑器 '{}' 异常退出 ({})，已取消操作", editor, status);
+    }
+
+    let edited = fs::read_to_string(&path).context("无法读取编辑后的内容")?;
+    let content = strip_template(&edited, &instructions);
+    Ok((!content.is_empty()).then_some(content))
+}
+
+/// 模板中剪刀线以下的说明行
+fn instructions(notes: &[String]) -> Vec<String> {
+    let mut lines = vec![
+        "# 请在上方输入日志内容，不要修改或删除上面这一行，它以下的内容会被忽略。".to_string(),
+        "# 内容为空时将取消操作。".to_string(),
+    ];
+    lines.extend(notes.iter().map(|note| format!("# {}", note)));
+    lines
+}
+
+/// 删除剪刀线及其以下的部分；剪刀线被删掉时，删除与说明行 `instructions` 相同的行
+fn strip_template(edited: &str, instructions: &[String]) -> String {
+    let scissors = edited.lines().position(|line| line.trim_end() == SCISSORS);
+    let content = match scissors {
+        Some(index) => {
+            let end = edited.split_inclusive('\n').take(index).map(str::len).sum();
+            edited[..end].to_string()
+        }
+        None => edited
+            .lines()
+            .filter(|line| !instructions.iter().any(|note| note == line.trim_end()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    content.trim().to_string()
+}
+
+/// 依次使用 `$VISUAL`、`$EDITOR`，都未设置时使用 `vi`
+fn editor_command() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notes() -> Vec<String> {
+        instructions(&["目录: /work".to_string(), "标签: rust".to_string()])
+    }
+
+    #[test]
+    fn only_the_block_below_the_scissors_is_removed() {
+        let edited = format!(
+            "# 标题\n\n正文\n#hashtag\n\n{}\n# 说明\n写在下面\n",
+            SCISSORS
+        );
+        assert_eq!(
+            strip_template(&edited, &notes()),
+            "# 标题\n\n正文\n#hashtag"
+        );
+    }
+
+    #[test]
+    fn instructions_are_removed_when_the_scissors_line_is_deleted() {
+        let edited = format!("# 标题\n正文\n\n{}\n", notes().join("\n"));
+        assert_eq!(strip_template(&edited, &notes()), "# 标题\n正文");
+        // 用户自己写的注释行保留
+        assert_eq!(
+            strip_template("# 标题\n# 目录: /elsewhere\n", &notes()),
+            "# 标题\n# 目录: /elsewhere"
+        );
+        assert_eq!(strip_template(&notes().join("\n"), &notes()), "");
+        assert_eq!(
+            strip_template(&format!("\n{}\n# 说明\n", SCISSORS), &notes()),
+            ""
+        );
+    }
+}
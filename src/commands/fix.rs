@@ -1,14 +1,101 @@
 // src/commands/fix.rs
 
+use crate::commands::editor;
 use crate::FixArgs;
 use anyhow::Result;
-use std::path::PathBuf;
-
-pub fn handle_fix(args: &FixArgs, db_path: &PathBuf) -> Result<()> {
-    println!("--- Running Command: Fix ---");
-    println!("Received args: {:?}", args);
-    println!("Database path target: {}", db_path.display());
-    println!("--------------------------\n");
-    // TODO: 实现单条日志的精确更新逻辑
+use dlog::{tags, Log, LogUpdate, Store};
+use std::path::{Component, PathBuf};
+
+pub fn handle_fix(args: &FixArgs, store: &mut Store) -> Result<()> {
+    if args.content.is_none() && !args.edit && args.tag.is_none() && args.directory.is_none() {
+        println!("未提供任何需要修改的字段 (--content / --edit / --tag / --directory)，已取消操作。");
+        return Ok(());
+    }
+
+    // 1. 根据短哈希定位日志
+    let id = store.resolve(&args.identifier)?;
+    let before = store.get(id)?;
+
+    // 2. 需要时在编辑器中修改现有内容
+    let content = if args.edit {
+        let hash = before.get_hash();
+        let notes = [
+            format!("日志: {}", store.abbreviations()?.abbreviate(&hash)),
+            format!("目录: {}", before.directory),
+            format!("标签: {}", before.tags.as_deref().unwrap_or("(无)")),
+        ];
+        match editor::edit(&before.content, &notes)? {
+            Some(content) => Some(content),
+            None => {
+                println!("日志内容为空，已取消操作。");
+                return Ok(());
+            }
+        }
+    } else {
+        args.content.clone()
+    };
+
+    // 3. 在一个事务中应用所有修改
+    let update = LogUpdate {
+        content,
+        tags: args.tag.as_deref().map(tags::parse_tag_list),
+        directory: args
+            .directory
+            .as_deref()
+            .map(|dir| normalize_directory(store, dir))
+            .transpose()?,
+    };
+    let after = store.update(id, &update)?;
+
+    // 4. 向用户展示修改前后的对比
+    let hash = after.get_hash();
+    println!("✅ 日志 {} 已更新。", store.abbreviations()?.abbreviate(&hash));
+    println!("--- 修改前 ---");
+    print_log(&before);
+    println!("--- 修改后 ---");
+    print_log(&after);
+
     Ok(())
 }
+
+/// 将用户提供的目录转换为数据库中存储的形式。
+///
+/// `global` 保持不变，相对路径会基于当前工作目录展开为绝对路径，
+/// 项目日志中再转换为相对项目根目录的形式。
+pub(crate) fn normalize_directory(store: &Store, directory: &str) -> Result<String> {
+    if directory == "global" {
+        return Ok(directory.to_string());
+    }
+
+    let path = PathBuf::from(directory);
+    let absolute = if path.is_absolute() {
+        path
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    // 目录存在时直接规范化；不存在时按字面去掉 `.` 与 `..` 成分
+    let absolute = absolute.canonicalize().unwrap_or_else(|_| {
+        let mut cleaned = PathBuf::new();
+        for component in absolute.components() {
+            match component {
+                Component::CurDir => {}
+                Component::ParentDir => {
+                    cleaned.pop();
+                }
+                other => cleaned.push(other),
+            }
+        }
+        cleaned
+    });
+
+    store.directory_key(&absolute)
+}
+
+fn print_log(log: &Log) {
+    println!("Time : {}", log.get_display_time());
+    println!("Dir  : {}", log.directory);
+    if let Some(tags) = &log.tags {
+        println!("Tags : {}", tags);
+    }
+    println!("Log  : {}", log.content);
+}
@@ -0,0 +1,40 @@
+// src/commands/format.rs
+
+use std::time::Duration;
+
+/// 形如 `850ms`、`3.2s`、`2m05s`、`1h30m`
+pub(crate) fn format_duration(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, secs % 3600 / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else if secs >= 1 {
+        format!("{:.1}s", elapsed.as_secs_f64())
+    } else {
+        format!("{}ms", elapsed.as_millis())
+    }
+}
+
+/// 终端中的显示宽度，非 ASCII 字符 (如中文) 按两列计算
+pub(crate) fn display_width(text: &str) -> usize {
+    text.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// 在右侧补齐空格到显示宽度 `width`
+pub(crate) fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(display_width(text)))
+    )
+}
+
+/// 在左侧补齐空格到显示宽度 `width`
+pub(crate) fn pad_left(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        " ".repeat(width.saturating_sub(display_width(text))),
+        text
+    )
+}
@@ -1,97 +1,66 @@
 // src/commands/get.rs
 
-use crate::GetArgs;
-use anyhow::{Context, Result as AnyhowResult};
-use rusqlite::{params_from_iter, Connection, Result, Row, ToSql};
-use std::path::PathBuf;
-use chrono::{DateTime, FixedOffset, ParseError, Local};
+use crate::commands::fix::normalize_directory;
+use crate::commands::format::{display_width, format_duration, pad, pad_left};
+use crate::{FilterArgs, GetArgs};
+use anyhow::{bail, Context, Result};
+use chrono::{TimeDelta, Utc};
+use dlog::ident::Abbreviations;
+use dlog::timeparse::{self, UserTimeZone};
+use dlog::{GroupBy, Level, Log, Query, Store, Unit};
 use serde::Serialize;
-use std::fmt;
-
-// 定义一个 LogEntry 结构体，用于在最后阶段存放和显示日志数据
-#[derive(Debug, Clone, Serialize)]
-struct Log {
-    id: i32,
-    timestamp: String,
-    directory: String,
-    content: String,
-    tags: Option<String>,
-    metadata: Option<String>,
-    level: Option<String>,
-}
-
-impl Log {
-    /// 从数据库行转换为 Log 结构体
-    fn from_row(row: &Row) -> Result<Self> {
-        Ok(Log {
-            id: row.get("id")?,
-            timestamp: row.get("timestamp")?,
-            directory: row.get("directory")?,
-            content: row.get("content")?,
-            tags: row.get("tags")?,
-            metadata: row.get("metadata")?,
-            level: row.get("level")?,
-        })
-    }
-
-    /// 将 UTC 时间转换为本地时间字符串
-    fn get_local_time(&self) -> Option<String> {
-        // 复用你现有的时间解析逻辑
-        let parse_result = DateTime::parse_from_rfc3339(&self.timestamp)
-            .or_else(|_| DateTime::parse_from_str(&self.timestamp, "%+"))
-            .or_else(|_| manual_time_parse(&self.timestamp));
-
-        parse_result.ok().map(|utc_time| {
-            let local_time = utc_time.with_timezone(&Local);
-            local_time.format("%Y-%m-%d %H:%M:%S").to_string()
-        })
-    }
-
-    /// 从 metadata 字段获取短哈希标识
-    fn get_short_hash(&self) -> String {
-        // 如果 metadata 字段存在且包含哈希值，则使用它
-        if let Some(metadata) = &self.metadata {
-            // 假设 metadata 字段直接存储哈希值
-            // 如果 metadata 是 JSON 格式，可能需要解析
-            metadata.clone()
-        } else {
-            // 如果没有 metadata，生成一个简单的备用标识
-            format!("{:x}", self.id)
-        }
-    }
-
-}
-
-// 为 Log 实现 Display trait，用于简洁模式
-impl fmt::Display for Log {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let local_time = self.get_local_time().unwrap_or_else(|| self.timestamp.clone());
-        /*
-        let content_preview = if self.content.len() > 30 {
-            format!("{}...", &self.content[..27])
-        } else {
-            self.content.clone()
-        };
-        */
-        let content_preview = self.content.clone();
-
-        write!(f, "{} | {}", local_time, content_preview)
-    }
-}
+use std::io::IsTerminal;
 
 // ====================================================================
 // 主处理函数 (The Conductor)
 // 它的职责是：调用选择器，然后根据情况调用行动器或格式化器。
 // ====================================================================
-pub fn handle_get(args: &GetArgs, db_path: &PathBuf) -> rusqlite::Result<()> {
-    let conn = Connection::open(db_path)?;
-
+pub fn handle_get(args: &GetArgs, store: &mut Store) -> Result<()> {
     // --- 第一步：筛选 ID ---
-    let ids = select_log_ids(&conn, args);
-    let ids = match ids {
-        Ok(ids) => ids,
-        Err(e) => panic!("{:?}", e),
+    let query = build_query(args, store)?;
+
+    // --- 聚合查询：只输出条数，不读取日志 ---
+    let group = match args.group_by.as_deref() {
+        Some("day") => Some(GroupBy::Day),
+        Some("week") => Some(GroupBy::Week),
+        Some("month") => Some(GroupBy::Month),
+        Some("tag") => Some(GroupBy::Tag),
+        Some("directory") => Some(GroupBy::Directory),
+        _ => None,
     };
+    if let Some(group) = group {
+        if args.add_tag.is_some() || args.fix_path.is_some() || args.delete {
+            bail!(
+                "--group-by {} 只输出统计结果，不能与动作参数一起使用",
+                args.group_by.as_deref().unwrap_or_default()
+            );
+        }
+        let tz = UserTimeZone::from_env()?;
+        let counts = store.count_by(&query, group, &tz)?;
+        if args.format == "json" {
+            let counts: Vec<serde_json::Value> = counts
+                .iter()
+                .map(|(key, count)| serde_json::json!({ "key": key, "count": count }))
+                .collect();
+            print_json(&counts);
+        } else if counts.is_empty() {
+            println!("未找到匹配的日志。");
+        } else {
+            print_counts(&counts);
+        }
+        return Ok(());
+    }
+    if args.count && args.group_by.is_none() {
+        let count = store.count(&query)?;
+        if args.format == "json" {
+            print_json(&serde_json::json!({ "count": count }));
+        } else {
+            println!("{}", count);
+        }
+        return Ok(());
+    }
+
+    let ids = store.find_ids(&query)?;
 
     // println!("IDs Found: {:?}", ids);
 
@@ -104,24 +73,25 @@ pub fn handle_get(args: &GetArgs, db_path: &PathBuf) -> rusqlite::Result<()> {
 
     // 检查是否有动作参数被提供
     let action_was_taken = if let Some(tag_to_add) = &args.add_tag {
-        // ... (在这里实现 UPDATE tags 的逻辑) ...
+        let updated = store.add_tag(&ids, tag_to_add)?;
         println!(
-            "为 {} 条日志添加了标签 '{}' (功能待实现)",
+            "✅ 命中 {} 条日志，其中 {} 条追加了标签 '{}' (其余已包含该标签)。",
             ids.len(),
-            tag_to_add
+            updated,
+            tag_to_add.trim()
         );
         true
-    } else if let Some(_new_path) = &args.fix_path {
-        // ... (在这里实现 UPDATE directory 的逻辑) ...
-        println!(
-            "将 {} 条日志的目录修改为 '{}' (功能待实现)",
-            ids.len(),
-            _new_path
-        );
+    } else if let Some(new_path) = &args.fix_path {
+        let new_path = normalize_directory(store, new_path)?;
+        let updated = store.set_directory(&ids, &new_path)?;
+        println!("✅ 已将 {} 条日志的目录修改为 '{}'。", updated, new_path);
         true
     } else if args.delete {
-        // ... (在这里实现 INSERT INTO backup 和 DELETE 的逻辑) ...
-        println!("删除了 {} 条日志 (功能待实现)", ids.len());
+        let moved = store.move_to_backup(&ids)?;
+        println!(
+            "✅ 已将 {} 条日志移动到备份区，可使用 `dlog recover` 恢复。",
+            moved
+        );
         true
     } else {
         // 没有提供任何动作参数
@@ -130,14 +100,70 @@ pub fn handle_get(args: &GetArgs, db_path: &PathBuf) -> rusqlite::Result<()> {
 
     // --- 第三步：如果没有执行动作，则获取完整日志并打印 ---
     if !action_was_taken {
-        let logs = get_logs_by_ids(&conn, &ids)?;
-
-        // 根据参数选择输出格式（这里需要为 GetArgs 添加 format 字段）
-        match args.format.as_str() {
-            "tags" => format_detailed1(&logs),
-            "iden" => format_detailed2(&logs),
-            "json" => format_json(&logs),
-            _ => format_compact(&logs), // 默认为简洁模式
+        let mut logs = store.get_by_ids(&ids)?;
+
+        // 全文检索时，为命中的日志附加高亮片段。
+        // 输出到终端时使用 ANSI 颜色高亮，否则使用方括号
+        let color = use_color();
+        if let Some(search) = query.search_query() {
+            let (open, close) = if color {
+                ("\x1b[1;33m", "\x1b[0m")
+            } else {
+                ("[", "]")
+            };
+            let mut snippets = store.snippets(search, &ids, open, close)?;
+            for log in &mut logs {
+                log.snippet = snippets.remove(&log.id);
+            }
+        }
+
+        // 根据参数选择输出格式
+        let abbrevs = store.abbreviations()?;
+        let print = |logs: &[Log]| match args.format.as_str() {
+            "tags" => format_detailed1(logs),
+            "iden" => format_detailed2(logs, &abbrevs),
+            "json" => format_json(logs),
+            _ => format_compact(logs, color), // 默认为简洁模式
+        };
+
+        match args.group_by.as_deref() {
+            Some("unit") if args.count => {
+                let groups = group_by_unit(store, logs);
+                if args.format == "json" {
+                    let counts: Vec<serde_json::Value> = groups
+                        .iter()
+                        .map(
+                            |(unit, logs)| serde_json::json!({ "unit": unit, "count": logs.len() }),
+                        )
+                        .collect();
+                    print_json(&counts);
+                } else {
+                    let counts: Vec<(String, usize)> = groups
+                        .iter()
+                        .map(|(unit, logs)| {
+                            let name = unit.as_ref().map_or_else(
+                                || "(不属于任何时间单元)".to_string(),
+                                Unit::to_string,
+                            );
+                            (name, logs.len())
+                        })
+                        .collect();
+                    print_counts(&counts);
+                }
+            }
+            Some("unit") => {
+                let groups = group_by_unit(store, logs);
+                if args.format == "json" {
+                    format_unit_groups_json(&groups);
+                } else {
+                    for (unit, logs) in &groups {
+                        print_unit_header(unit.as_ref(), logs.len(), color);
+                        print(logs);
+                        println!();
+                    }
+                }
+            }
+            _ => print(&logs),
         }
     }
 
@@ -148,201 +174,336 @@ pub fn handle_get(args: &GetArgs, db_path: &PathBuf) -> rusqlite::Result<()> {
 // ====================================================================
 // [DONE] 输出结果可复用（原子测试其一）
 // 阶段一：选择器 (Selector)
-// 这个函数的唯一职责是：根据所有筛选参数，返回一个目标日志的 ID 列表。
+// 这个函数的唯一职责是：把命令行的筛选参数翻译为 `Query`。
 // ====================================================================
-fn select_log_ids(conn: &Connection, args: &GetArgs) -> AnyhowResult<Vec<i32>> {
-    let mut conditions: Vec<String> = Vec::new();
-    let mut params: Vec<Box<dyn ToSql>> = Vec::new();
-
-    // 构建 WHERE 子句的逻辑和之前完全一样
-    if !args.all {
-        let current_dir = std::env::current_dir()?
-            .to_str()
-            .context("无法将当前目录转换为字符串")?
-            .to_string();
-
-        if args.recursive {
-            if args.global {
-                conditions.push("(directory LIKE ? OR directory == 'global')".to_string());
-            } else {
-                conditions.push("directory LIKE ?".to_string());
-            }
-            params.push(Box::new(format!("{}%", current_dir)));
-        } else {
-            if args.global {
-                conditions.push("(directory = ? OR directory == 'global')".to_string());
-            } else {
-                conditions.push("directory = ?".to_string());
-            }
-            params.push(Box::new(current_dir));
-        }
+pub(crate) fn build_query(args: &GetArgs, store: &Store) -> Result<Query> {
+    let query = filter_query(&args.filter, store)?;
+
+    // 默认只显示最新的一条，按会话、时间单元查询或分组显示时默认显示全部
+    let show_all =
+        args.filter.session.is_some() || args.filter.unit.is_some() || args.group_by.is_some();
+    let limit = args.num.or(if show_all { None } else { Some(1) });
+    let query = match limit {
+        Some(limit) => query.limit(limit),
+        None => query,
+    };
+
+    Ok(query.reverse(args.reverse))
+}
+
+/// 把筛选参数翻译为 `Query`，不包含条数限制与排序。
+pub(crate) fn filter_query(args: &FilterArgs, store: &Store) -> Result<Query> {
+    let mut query = Query::new();
+
+    // 会话与时间单元中的日志分布在各个目录，按它们查询时不限制目录
+    let session = match &args.session {
+        Some(key) => Some(store.find_session(key)?),
+        None => None,
+    };
+    let unit = match &args.unit {
+        Some(key) => Some(store.find_unit(key)?),
+        None => None,
+    };
+
+    // 目录范围
+    if !args.all && session.is_none() && unit.is_none() {
+        let current_dir = store.directory_key(&std::env::current_dir()?)?;
+        query = query
+            .directory(current_dir)
+            .recursive(args.recursive)
+            .include_global(args.global);
     }
 
+    // 时间条件彼此独立，可以与其他任意筛选条件自由组合。
+    // 用户给出的日期按用户时区解释，换算成 UTC 区间后与数据库中的时间戳比较。
+    let tz = UserTimeZone::from_env()?;
     if args.today {
-        conditions.push("DATE(timestamp) = DATE('now', 'localtime')".to_string());
-    } else if let Some(date) = &args.date {
-        conditions.push("DATE(timestamp) = ?".to_string());
-        params.push(Box::new(date.clone()));
-    } else if let Some(hour) = &args.hour {
-        conditions.push("timestamp >= datetime('now', ?)".to_string());
-        params.push(Box::new(format!("-{} hours", hour)));
-    } else if let Some(minute) = &args.minute {
-        conditions.push("timestamp >= datetime('now', ?)".to_string());
-        params.push(Box::new(format!("-{} minutes", minute)));
+        query = query.on_day(&tz, tz.now().date_naive())?;
+    }
+    if let Some(date) = &args.date {
+        query = query.on_day(&tz, timeparse::parse_date(date)?)?;
+    }
+    if let Some(hour) = args.hour {
+        let duration =
+            TimeDelta::try_hours(hour.into()).with_context(|| format!("小时数过大: {}", hour))?;
+        query = query.within(duration)?;
+    }
+    if let Some(minute) = args.minute {
+        let duration = TimeDelta::try_minutes(minute.into())
+            .with_context(|| format!("分钟数过大: {}", minute))?;
+        query = query.within(duration)?;
+    }
+    if let Some(since) = &args.since {
+        query = query.since(timeparse::parse_time_spec(since, &tz)?.as_lower_bound());
+    }
+    if let Some(until) = &args.until {
+        query = query.until(timeparse::parse_time_spec(until, &tz)?.as_upper_bound());
     }
 
-
+    // 内容匹配
     if let Some(tag) = &args.tag {
-        conditions.push("tags LIKE ?".to_string());
-        params.push(Box::new(format!("%{}%", tag)));
+        query = query.tag(tag).include_subtags(args.subtags);
     }
-
     if let Some(keyword) = &args.keyword {
-        conditions.push("content LIKE ?".to_string());
-        params.push(Box::new(format!("%{}%", keyword)));
+        query = query.keyword(keyword);
+    }
+    if let Some(search) = &args.search {
+        query = query.search(search);
     }
 
-    // 组装最终的 SELECT id 查询
-    let where_clause = if !conditions.is_empty() {
-        format!(" WHERE {}", conditions.join(" AND "))
-    } else {
-        "".to_string()
-    };
-
-
+    // git 上下文
+    if let Some(branch) = &args.branch {
+        query = query.branch(branch);
+    }
+    if let Some(commit) = &args.commit {
+        query = query.commit(commit);
+    }
 
-    let mut sql = format!("SELECT id FROM logs{}", where_clause);
-    if !args.reverse {
-        sql.push_str(" ORDER BY timestamp DESC LIMIT ?");
-    } else {
-        sql.push_str(" ORDER BY timestamp ASC LIMIT ?");
+    if let Some(level) = args.level {
+        query = query.level(level);
     }
-    params.push(Box::new(args.num));
+    query = query.hide_done(args.hide_done);
 
-    // println!("Final SQL Code: [{:?}]", sql);
+    // 用户元数据
+    for field in &args.meta {
+        query = match field.split_once('=') {
+            Some((key, value)) => query.meta(key.trim(), Some(value.to_string())),
+            None => query.meta(field.trim(), None),
+        };
+    }
 
-    // 执行查询并收集 ID
-    let mut stmt = conn.prepare(&sql)?;
-    let ids_iter = stmt.query_map(params_from_iter(params), |row| row.get(0))?;
+    if let Some(session) = &session {
+        query = query.session(session.id);
+    }
 
+    if let Some(unit) = &unit {
+        query = query.unit(&unit.mark);
+    }
 
-    let ids = ids_iter.collect::<Result<Vec<i32>, _>>()?;
-    Ok(ids)
+    Ok(query)
 }
 
+
 // ====================================================================
-// 超集采集器
+// 分组 (Grouping)
 // ====================================================================
-fn get_logs_by_ids(conn: &Connection, ids: &[i32]) -> Result<Vec<Log>> {
-    // 处理空 ID 列表的情况
-    if ids.is_empty() {
-        return Ok(Vec::new());
-    }
 
-    // 创建占位符字符串
-    let placeholders: Vec<String> = (0..ids.len()).map(|_| "?".to_string()).collect();
-    let placeholders_str = placeholders.join(",");
-
-    // 构建 SQL 查询
-    let sql = format!(
-        "SELECT id, timestamp, directory, content, tags, metadata, level
-         FROM logs
-         WHERE id IN ({})",
-        placeholders_str
-    );
-
-    // 准备查询语句
-    let mut stmt = conn.prepare(&sql)?;
-
-    // 将 ID 转换为参数
-    let params: Vec<&dyn rusqlite::ToSql> =
-        ids.iter().map(|id| id as &dyn rusqlite::ToSql).collect();
-
-    // 执行查询并收集结果
-    let log_iter = stmt.query_map(params.as_slice(), |row| Log::from_row(row))?;
-
-    let mut logs = Vec::new();
-    for log_result in log_iter {
-        logs.push(log_result?);
+/// 按时间单元分组，组的顺序即每组第一条日志在结果中出现的顺序。
+/// 不属于任何时间单元的日志归入 None。
+fn group_by_unit(store: &Store, logs: Vec<Log>) -> Vec<(Option<Unit>, Vec<Log>)> {
+    let mut groups: Vec<(Option<String>, Vec<Log>)> = Vec::new();
+    for log in logs {
+        let mark = log.get_metadata().and_then(|metadata| metadata.unit);
+        match groups.iter_mut().find(|(m, _)| *m == mark) {
+            Some((_, group)) => group.push(log),
+            None => groups.push((mark, vec![log])),
+        }
     }
 
-    Ok(logs)
+    groups
+        .into_iter()
+        .map(|(mark, logs)| {
+            // 时间单元记录被删除时，仍然按标记分组
+            let unit = mark.map(|mark| {
+                store.unit(&mark).unwrap_or_else(|_| Unit {
+                    mark,
+                    name: None,
+                    started_at: String::new(),
+                    ended_at: None,
+                })
+            });
+            (unit, logs)
+        })
+        .collect()
 }
 
+/// 每行一组：名称与条数，空的名称 (没有标签的日志) 显示为 `(无标签)`
+fn print_counts(counts: &[(String, usize)]) {
+    let name = |key: &str| {
+        if key.is_empty() {
+            "(无标签)".to_string()
+        } else {
+            key.to_string()
+        }
+    };
+    let width = counts
+        .iter()
+        .map(|(key, _)| display_width(&name(key)))
+        .max()
+        .unwrap_or(0);
+    let count_width = counts
+        .iter()
+        .map(|(_, count)| count.to_string().len())
+        .max()
+        .unwrap_or(0);
+    for (key, count) in counts {
+        println!(
+            "{}  {}",
+            pad(&name(key), width),
+            pad_left(&count.to_string(), count_width)
+        );
+    }
+}
 
+/// 形如 `=== k3x (parser bug) | 2025-10-01 14:00:00 → 15:30:12 | 1h30m | 5 条 ===`
+fn print_unit_header(unit: Option<&Unit>, count: usize, color: bool) {
+    let header = match unit {
+        Some(unit) if !unit.started_at.is_empty() => {
+            let tz = UserTimeZone::from_env().unwrap_or(UserTimeZone::Local);
+            let end = match &unit.ended_at {
+                Some(end) => tz.display_rfc3339(end),
+                None => "进行中".to_string(),
+            };
+            format!(
+                "{} | {} → {} | {} | {} 条",
+                unit,
+                tz.display_rfc3339(&unit.started_at),
+                end,
+                format_duration(unit.duration()),
+                count
+            )
+        }
+        Some(unit) => format!("{} | {} 条", unit, count),
+        None => format!("(不属于任何时间单元) | {} 条", count),
+    };
+    if color {
+        println!("\x1b[1m=== {} ===\x1b[0m", header);
+    } else {
+        println!("=== {} ===", header);
+    }
+}
 
 // ====================================================================
 // 阶段三：格式化输出 (Formatter)
 // 这个函数的职责是：接收日志数据和显示选项，然后漂亮地打印它们。
 // ====================================================================
 
-/// 简洁模式：时间 | content 字段的前 30 个字
-fn format_compact(logs: &[Log]) {
+/// 是否使用 ANSI 颜色：输出到终端，且用户没有设置 NO_COLOR
+pub(crate) fn use_color() -> bool {
+    std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+}
+
+/// 简洁模式：时间 | 级别标记 content 字段的前 30 个字
+///
+/// info 级别的日志不显示标记，保持与旧版本相同的样式。
+fn format_compact(logs: &[Log], color: bool) {
     for log in logs {
-        println!("{}", log); // 使用 Display trait 的实现
+        let level = log.get_level();
+        if level == Level::Info {
+            println!("{}", log); // 使用 Display trait 的实现
+            continue;
+        }
+
+        let marker = format!("[{}]", level.as_str().to_uppercase());
+        let marker = if color {
+            let code = match level {
+                Level::Warn => "33",
+                Level::Error => "31",
+                Level::Todo => "36",
+                Level::Done => "32",
+                Level::Info => "0",
+            };
+            format!("\x1b[1;{}m{}\x1b[0m", code, marker)
+        } else {
+            marker
+        };
+        let local_time = log.get_local_time().unwrap_or_else(|| log.timestamp.clone());
+        let content = log.snippet.as_deref().unwrap_or(&log.content);
+        println!("{} | {} {}", local_time, marker, content);
     }
 }
 
 /// 详细模式1：时间 | 目录 | tags | 完整 content
 fn format_detailed1(logs: &[Log]) {
     for log in logs {
-        let local_time = log.get_local_time().unwrap_or_else(|| log.timestamp.clone());
-
-        println!("Time : {}", local_time);
+        println!("Time : {}", log.get_display_time());
         println!("Dir  : {}", log.directory);
 
         if let Some(tags) = &log.tags {
             println!("Tags : {}", tags);
         }
 
+        if log.get_level() != Level::Info {
+            println!("Lvl  : {}", log.get_level());
+        }
+
+        if let Some(metadata) = log.get_metadata() {
+            if let Some(due) = &metadata.due {
+                println!("Due  : {}", due);
+            }
+            if let Some(done_at) = &metadata.done_at {
+                let tz = UserTimeZone::from_env().unwrap_or(UserTimeZone::Local);
+                println!("Done : {}", tz.display_rfc3339(done_at));
+            }
+            if let Some(git) = &metadata.git {
+                println!("Git  : {}", git);
+            }
+            if !metadata.meta.is_empty() {
+                let fields: Vec<String> =
+                    metadata.meta.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+                println!("Meta : {}", fields.join(", "));
+            }
+        }
+
+        if let Some(unit) = log.get_metadata().and_then(|metadata| metadata.unit) {
+            println!("Unit : {}", unit);
+        }
+
+        if let Some((start, end)) = log.clock_span() {
+            let tz = UserTimeZone::from_env().unwrap_or(UserTimeZone::Local);
+            let end_time = match end {
+                Some(end) => tz.convert(end).format("%Y-%m-%d %H:%M:%S").to_string(),
+                None => "计时中".to_string(),
+            };
+            let elapsed = (end.unwrap_or_else(Utc::now) - start).to_std().unwrap_or_default();
+            println!(
+                "Span : {} → {} ({})",
+                tz.convert(start).format("%Y-%m-%d %H:%M:%S"),
+                end_time,
+                format_duration(elapsed)
+            );
+        }
+
+        if let Some(snippet) = &log.snippet {
+            println!("Hit  : {}", snippet);
+        }
+
         println!("Log  : {}", log.content);
         println!("---"); // 分隔线
     }
 }
 
 /// 详细模式2：时间 | 短哈希标识 | 完整 content
-fn format_detailed2(logs: &[Log]) {
+fn format_detailed2(logs: &[Log], abbrevs: &Abbreviations) {
     for log in logs {
-        let local_time = log.get_local_time().unwrap_or_else(|| log.timestamp.clone());
-        let short_hash = log.get_short_hash();
+        let hash = log.get_hash();
+        let short_hash = abbrevs.abbreviate(&hash);
 
-        println!("Time : {}", local_time);
+        println!("Time : {}", log.get_display_time());
         println!("Hash : {}", short_hash);
         println!("Log  : {}", log.content);
         println!("---"); // 分隔线
     }
 }
 
+/// 按时间单元分组的 JSON：`[{"unit": {...} | null, "logs": [...]}, ...]`
+fn format_unit_groups_json(groups: &[(Option<Unit>, Vec<Log>)]) {
+    let groups: Vec<serde_json::Value> = groups
+        .iter()
+        .map(|(unit, logs)| serde_json::json!({ "unit": unit, "logs": logs }))
+        .collect();
+    print_json(&groups);
+}
+
 /// JSON 模式：打印 Json 样式的信息
 fn format_json(logs: &[Log]) {
-    match serde_json::to_string_pretty(&logs) {
+    print_json(logs);
+}
+
+/// 以缩进的 JSON 打印任意可序列化的结果，其他子命令的 JSON 输出也使用它
+pub(crate) fn print_json<T: Serialize + ?Sized>(value: &T) {
+    match serde_json::to_string_pretty(value) {
         Ok(json) => println!("{}", json),
         Err(e) => eprintln!("JSON 序列化失败: {}", e),
     }
 }
-
-fn manual_time_parse(time_str: &str) -> Result<DateTime<FixedOffset>, ParseError> {
-    // 移除纳秒部分后的精度，保留最多6位小数
-    let simplified = if let Some(dot_pos) = time_str.find('.') {
-        if let Some(z_pos) = time_str[dot_pos..].find(|c| c == 'Z' || c == '+') {
-            let decimal_part = &time_str[dot_pos+1..dot_pos+z_pos];
-            let limited_decimal = if decimal_part.len() > 6 {
-                &decimal_part[..6] // 限制为6位小数
-            } else {
-                decimal_part
-            };
-
-            format!("{}.{}{}", 
-                    &time_str[..dot_pos], 
-                    limited_decimal,
-                    &time_str[dot_pos+z_pos..])
-        } else {
-            time_str.to_string()
-        }
-    } else {
-        time_str.to_string()
-    };
-
-    DateTime::parse_from_rfc3339(&simplified)
-}
-
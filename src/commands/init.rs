@@ -1,32 +1,74 @@
 // src/commands/init.rs
 
-use crate::{db, InitArgs};
+use crate::InitArgs;
 use anyhow::Result;
-use std::path::PathBuf;
+use dlog::{db, location, Store};
+use std::path::Path;
 
-pub fn handle_init(_args: &InitArgs, db_path: &PathBuf) -> Result<()> {
+pub fn handle_init(_args: &InitArgs, db_path: &Path) -> Result<()> {
     // _args 暂时未使用，但为未来的 `upgrade` 功能保留
 
     if db_path.exists() {
         println!("数据库已存在于: {}", db_path.display());
+        // 旧版本的数据库在这里完成升级，与其他子命令一致
+        Store::open(db_path)?.migrate()?;
         println!("无需执行初始化操作。");
     } else {
         println!("数据库不存在，正在创建并初始化...");
-        match db::initialize_db(db_path) {
-            Ok(_) => {
-                println!("✅ 数据库成功初始化于: {}", db_path.display());
-                println!("   - 已创建 'logs', 'backup', 'configs' 表。");
-                println!(
-                    "   - 已为 'backup' 表设置自动清理触发器 (上限 {} 条)。",
-                    db::MAX_BACKUP_ENTRIES
-                ); // 这里的100可以从db模块导入常量
-            }
-            Err(e) => {
-                // 将 rusqlite::Error 转换为 anyhow::Error 并返回
-                return Err(e.into());
-            }
+        Store::init(db_path)?;
+        println!("✅ 数据库成功初始化于: {}", db_path.display());
+        println!("   - 已创建 'logs', 'backup', 'configs', 'tags', 'log_tags' 表。");
+        println!(
+            "   - 已为 'backup' 表设置自动清理触发器 (上限 {} 条)。",
+            db::MAX_BACKUP_ENTRIES
+        ); // 这里的100可以从db模块导入常量
+        if let Some(root) = location::project_root_of(db_path) {
+            println!(
+                "   - 这是 {} 的项目日志，项目内的目录以相对路径记录，可将 .dlog/ 提交到版本库中。",
+                root.display()
+            );
         }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    fn db_version(path: &Path) -> String {
+        Connection::open(path)
+            .unwrap()
+            .query_row(
+                "SELECT value FROM configs WHERE key = 'db_version'",
+                [],
+                |row| row.get(0),
+            )
+            .unwrap()
+    }
+
+    #[test]
+    fn init_upgrades_an_existing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("dlog.db");
+        let args = InitArgs {
+            upgrade: false,
+            here: false,
+        };
+
+        handle_init(&args, &path).unwrap();
+        assert_eq!(db_version(&path), db::DLOG_DB_VERSION.to_string());
+
+        Connection::open(&path)
+            .unwrap()
+            .execute(
+                "UPDATE configs SET value = '9' WHERE key = 'db_version'",
+                (),
+            )
+            .unwrap();
+        handle_init(&args, &path).unwrap();
+        assert_eq!(db_version(&path), db::DLOG_DB_VERSION.to_string());
+    }
+}
@@ -1,39 +1,30 @@
 // src/commands/log.rs
 
+use crate::commands::editor;
 use crate::LogArgs;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use chrono::Utc;
-use rusqlite::Connection;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-use std::io::{self, BufRead};
-use std::path::PathBuf;
-
-/// 计算给定字符串的 u64 哈希值。
-fn calculate_hash<T: Hash>(t: &T) -> u64 {
-    let mut s = DefaultHasher::new();
-    t.hash(&mut s);
-    s.finish()
-}
+use dlog::git::GitContext;
+use dlog::timeparse::UserTimeZone;
+use dlog::{config, tags, NewLog, Store};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, IsTerminal, Read};
 
-pub fn handle_log(args: &LogArgs, db_path: &PathBuf) -> Result<()> {
+pub fn handle_log(args: &LogArgs, store: &mut Store) -> Result<()> {
     // 1. 获取日志内容
-    let content = if let Some(message) = &args.message {
-        // 如果用户通过 -m 提供了短消息，直接使用
-        message.clone()
-    } else {
-        // 否则，进入交互式输入模式以获取长消息
-        println!("请输入日志内容 (按 Ctrl+D 结束):");
-        let mut lines = io::stdin().lock().lines();
-        let mut input_content = String::new();
-        while let Some(line) = lines.next() {
-            let line = line.context("无法从标准输入读取行")?;
-            input_content.push_str(&line);
-            input_content.push('\n');
-        }
-        // 移除最后一个多余的换行符
-        input_content.trim_end().to_string()
+    let content = read_content(args, store)?;
+    let content = match args.tail {
+        Some(n) => keep_last_lines(&content, n),
+        None => content,
     };
+    if content.len() > MAX_CONTENT_BYTES {
+        bail!(
+            "日志内容有 {} 字节，超过了 {} KiB 的上限，可以使用 --tail N 只保留最后 N 行",
+            content.len(),
+            MAX_CONTENT_BYTES / 1024
+        );
+    }
 
     // 如果内容为空，则不记录
     if content.is_empty() {
@@ -42,36 +33,242 @@ pub fn handle_log(args: &LogArgs, db_path: &PathBuf) -> Result<()> {
     }
 
     // 2. 准备要存入数据库的数据
-    let timestamp = Utc::now().to_rfc3339();
+    let new_log = NewLog {
+        meta: args.meta.iter().cloned().collect(),
+        level: args.level,
+        ..prepare_new_log(store, content, args.tags.as_deref(), args.global)?
+    };
+
+    // 3. 写入数据库
+    let log = store.insert(&new_log)?;
+
+    // 4. 向用户提供反馈
+    let hash = log.get_hash();
+    let short_hash = store.abbreviations()?.abbreviate(&hash).to_string(); // 像 git 一样显示最短的唯一前缀
+    println!("✅ 日志已成功记录！");
+    println!("   唯一标识: {}", short_hash);
+
+    Ok(())
+}
+
+/// 一条日志内容的大小上限
+const MAX_CONTENT_BYTES: usize = 1024 * 1024;
+
+/// 按优先级获取日志内容：`-m`、`-e`、`--file`、管道输入、配置的编辑器、交互输入。
+fn read_content(args: &LogArgs, store: &Store) -> Result<String> {
+    // 如果用户通过 -m 提供了短消息，直接使用
+    if let Some(message) = &args.message {
+        return Ok(message.clone());
+    }
+
+    if args.edit {
+        return edit_content(args, store);
+    }
+
+    if let Some(path) = &args.file {
+        let file = File::open(path).with_context(|| format!("无法读取文件 {}", path.display()))?;
+        // 不使用 --tail 时，在读取之前就拒绝过大的文件
+        let size = file.metadata()?.len();
+        if args.tail.is_none() && size > MAX_CONTENT_BYTES as u64 {
+            bail!(
+                "文件 {} 有 {} 字节，超过了 {} KiB 的上限，可以使用 --tail N 只保留最后 N 行",
+                path.display(),
+                size,
+                MAX_CONTENT_BYTES / 1024
+            );
+        }
+        return read_input(BufReader::new(file), args.tail);
+    }
+
+    // 标准输入来自管道或重定向时，静默读取全部内容，便于在脚本中使用
+    if !io::stdin().is_terminal() {
+        return read_input(io::stdin().lock(), args.tail);
+    }
+
+    let use_editor = store
+        .config_get(config::USE_EDITOR)?
+        .and_then(|value| config::parse_bool(&value))
+        .unwrap_or(false);
+    if use_editor {
+        return edit_content(args, store);
+    }
+
+    // 否则，进入交互式输入模式以获取长消息
+    println!("请输入日志内容 (按 Ctrl+D 结束):");
+    let lines = io::stdin().lock().lines();
+    let mut input_content = String::new();
+    for line in lines {
+        let line = line.context("无法从标准输入读取行")?;
+        input_content.push_str(&line);
+        input_content.push('\n');
+    }
+    // 移除最后一个多余的换行符
+    Ok(input_content.trim_end().to_string())
+}
+
+/// 读取文件或管道中的内容，内存占用有上限。
+///
+/// 给出 `tail` 时逐行读取，只保留最后 `tail` 行 (不计末尾的空行)，输入再大也可以使用；
+/// 保留的行合计超过 [`MAX_CONTENT_BYTES`] 时丢弃其中最早的行，单独一行超过上限时返回错误。
+/// 否则最多读取 [`MAX_CONTENT_BYTES`]，超出时返回错误。
+/// 构建输出中可能混有非 UTF-8 字节，不因此放弃整段内容。
+fn read_input(mut reader: impl BufRead, tail: Option<usize>) -> Result<String> {
+    let Some(n) = tail else {
+        let mut bytes = Vec::new();
+        reader
+            .take(MAX_CONTENT_BYTES as u64 + 1)
+            .read_to_end(&mut bytes)
+            .context("读取内容时出错")?;
+        if bytes.len() > MAX_CONTENT_BYTES {
+            bail!(
+                "输入超过了 {} KiB 的上限，可以使用 --tail N 只保留最后 N 行",
+                MAX_CONTENT_BYTES / 1024
+            );
+        }
+        return Ok(String::from_utf8_lossy(&bytes).trim_end().to_string());
+    };
+
+    // 空白行先放在 blanks 中，之后出现非空白的行时才计入，末尾的空白行因此不占用 N 行的名额。
+    // retained 是两者合计的字节数
+    let mut lines: VecDeque<Vec<u8>> = VecDeque::new();
+    let mut blanks: VecDeque<Vec<u8>> = VecDeque::new();
+    let mut retained = 0;
+    let keep = |queue: &mut VecDeque<Vec<u8>>, retained: &mut usize, line: Vec<u8>| {
+        queue.push_back(line);
+        if queue.len() > n {
+            *retained -= queue.pop_front().map_or(0, |line| line.len());
+        }
+    };
+    loop {
+        // 每次最多读取上限多一个字节，没有换行的超长输入不会全部读入内存
+        let mut line = Vec::new();
+        let read = (&mut reader)
+            .take(MAX_CONTENT_BYTES as u64 + 1)
+            .read_until(b'\n', &mut line)
+            .context("读取内容时出错")?;
+        if read == 0 {
+            break;
+        }
+        if line.len() > MAX_CONTENT_BYTES {
+            bail!("输入中有一行超过了 {} KiB 的上限", MAX_CONTENT_BYTES / 1024);
+        }
+        retained += line.len();
+        if line.iter().all(u8::is_ascii_whitespace) {
+            keep(&mut blanks, &mut retained, line);
+        } else {
+            for blank in blanks.drain(..) {
+                keep(&mut lines, &mut retained, blank);
+            }
+            keep(&mut lines, &mut retained, line);
+        }
+        while retained > MAX_CONTENT_BYTES {
+            let oldest = lines.pop_front().or_else(|| blanks.pop_front());
+            retained -= oldest.map_or(0, |line| line.len());
+        }
+    }
+    let bytes: Vec<u8> = lines.into_iter().flatten().collect();
+    Ok(String::from_utf8_lossy(&bytes).trim_end().to_string())
+}
+
+/// 在编辑器中书写，模板中注明日志将被记录到的目录与标签
+fn edit_content(args: &LogArgs, store: &Store) -> Result<String> {
     let directory = if args.global {
         "global".to_string()
     } else {
-        std::env::current_dir()?
-            .to_str()
-            .context("无法将当前目录转换为字符串")?
-            .to_string()
+        store.directory_key(&std::env::current_dir()?)?
     };
+    let tags = tags::parse_tag_list(args.tags.as_deref().unwrap_or_default());
+    let notes = [
+        format!("目录: {}", directory),
+        format!("标签: {}", if tags.is_empty() { "(无)".to_string() } else { tags.join(", ") }),
+    ];
+    Ok(editor::edit("", &notes)?.unwrap_or_default())
+}
 
-    // 3. 生成并存储哈希标识符
-    let hash_input = format!("{}{}", timestamp, content);
-    let hash = calculate_hash(&hash_input);
-    let metadata = format!("{:x}", hash); // 格式化为十六进制字符串
+/// 只保留最后 `n` 行
+pub(crate) fn keep_last_lines(content: &str, n: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    lines[lines.len().saturating_sub(n)..].join("\n")
+}
 
-    let tags = args.tags.clone().unwrap_or_default();
+/// 根据当前环境填写一条新日志的目录、时区与 git 上下文。
+pub(crate) fn prepare_new_log(
+    store: &Store,
+    content: String,
+    tags: Option<&str>,
+    global: bool,
+) -> Result<NewLog> {
+    // 时间戳统一以 UTC 存储，同时记录书写者所在时区的偏移，以便还原原始的墙上时间
+    let utc_offset = UserTimeZone::from_env()?
+        .convert(Utc::now())
+        .offset()
+        .local_minus_utc();
+    let current_dir = std::env::current_dir()?;
+    let (directory, git) = if global {
+        ("global".to_string(), None)
+    } else {
+        // 在 git 工作区中记录时，一并保存仓库、分支与提交信息
+        (
+            store.directory_key(&current_dir)?,
+            GitContext::discover(&current_dir),
+        )
+    };
+    let record_dirty = store
+        .config_get(config::GIT_RECORD_DIRTY)?
+        .and_then(|value| config::parse_bool(&value))
+        .unwrap_or(false);
+    let git = if record_dirty {
+        git.map(GitContext::with_dirty)
+    } else {
+        git
+    };
 
-    // 4. 连接数据库并插入数据
-    let conn =
-        Connection::open(db_path).context(format!("无法连接到数据库: {}", db_path.display()))?;
+    Ok(NewLog {
+        directory,
+        content,
+        tags: tags::parse_tag_list(tags.unwrap_or_default()),
+        utc_offset: Some(utc_offset),
+        git,
+        // 会话或时间单元进行期间，新的日志归入其中
+        session: store.active_session()?.map(|session| session.id),
+        unit: store.active_unit()?.map(|unit| unit.mark),
+        ..Default::default()
+    })
+}
 
-    conn.execute(
-        "INSERT INTO logs (timestamp, directory, content, tags, metadata) VALUES (?1, ?2, ?3, ?4, ?5)",
-        rusqlite::params![timestamp, directory, content, tags, metadata],
-    )?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // 5. 向用户提供反馈
-    let short_hash = &metadata[..7]; // 取哈希的前7位作为短哈希，更像git
-    println!("✅ 日志已成功记录！");
-    println!("   唯一标识: {}", short_hash);
+    #[test]
+    fn tail_keeps_the_last_lines_without_trailing_blanks() {
+        let input = "one\n\ntwo\nthree\n\n  \n";
+        assert_eq!(read_input(input.as_bytes(), Some(2)).unwrap(), "two\nthree");
+        assert_eq!(
+            read_input(input.as_bytes(), Some(3)).unwrap(),
+            "\ntwo\nthree"
+        );
+        assert_eq!(
+            read_input(input.as_bytes(), None).unwrap(),
+            "one\n\ntwo\nthree"
+        );
+    }
 
-    Ok(())
+    #[test]
+    fn endless_input_without_newlines_is_rejected() {
+        // io::repeat 永不结束，能返回说明读取的字节数有上限
+        for tail in [None, Some(10)] {
+            let reader = BufReader::new(io::repeat(b'x'));
+            assert!(read_input(reader, tail).is_err(), "{:?}", tail);
+        }
+    }
+
+    #[test]
+    fn tail_drops_the_oldest_lines_beyond_the_byte_limit() {
+        let long = "x".repeat(MAX_CONTENT_BYTES / 2);
+        let input = format!("{long}\n{long}\n{long}\nlast\n");
+        let content = read_input(input.as_bytes(), Some(10)).unwrap();
+        assert!(content.len() <= MAX_CONTENT_BYTES);
+        assert_eq!(content, format!("{long}\nlast"));
+    }
 }
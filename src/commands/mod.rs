@@ -1,5 +1,18 @@
+pub mod clock;
+pub mod config;
+pub mod editor;
 pub mod fix;
+pub mod format;
 pub mod get;
 pub mod init;
 pub mod log;
 pub mod pop;
+pub mod recover;
+pub mod report;
+pub mod run;
+pub mod session;
+pub mod shell_init;
+pub mod stats;
+pub mod tag;
+pub mod todo;
+pub mod unit;
@@ -1,14 +1,54 @@
 // src/commands/pop.rs
 
 use crate::PopArgs;
-use anyhow::Result;
-use std::path::PathBuf;
-
-pub fn handle_pop(args: &PopArgs, db_path: &PathBuf) -> Result<()> {
-    println!("--- Running Command: Pop ---");
-    println!("Received args: {:?}", args);
-    println!("Database path target: {}", db_path.display());
-    println!("--------------------------\n");
-    // TODO: 实现精确移除日志的逻辑
+use anyhow::{Context, Result};
+use dlog::Store;
+use std::io::{self, Write};
+
+pub fn handle_pop(args: &PopArgs, store: &mut Store) -> Result<()> {
+    // 1. 逐个解析短哈希，任何一个失败都会中止整个操作
+    let mut ids: Vec<i32> = Vec::new();
+    for identifier in &args.identifiers {
+        let id = store.resolve(identifier)?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+
+    // 2. 展示即将被移除的日志
+    let logs = store.get_by_ids(&ids)?;
+    let abbrevs = store.abbreviations()?;
+    println!("以下 {} 条日志将被移动到备份区:", logs.len());
+    for log in &logs {
+        println!("  {}  {}", abbrevs.abbreviate(&log.get_hash()), log);
+    }
+
+    // 3. 请求用户确认
+    if !args.yes && !confirm("确认移除吗? [y/N] ")? {
+        println!("已取消操作。");
+        return Ok(());
+    }
+
+    // 4. 复制到 backup 并从 logs 中删除，两者在同一个事务中完成
+    let moved = store.move_to_backup(&ids)?;
+
+    println!("✅ 已将 {} 条日志移动到备份区，可使用 `dlog recover` 恢复。", moved);
+
     Ok(())
 }
+
+/// 打印提示并从标准输入读取一行，只有输入 y/yes 时返回 true。
+pub(crate) fn confirm(prompt: &str) -> Result<bool> {
+    print!("{}", prompt);
+    io::stdout().flush()?;
+
+    let mut answer = String::new();
+    io::stdin()
+        .read_line(&mut answer)
+        .context("无法从标准输入读取确认信息")?;
+
+    Ok(matches!(
+        answer.trim().to_lowercase().as_str(),
+        "y" | "yes"
+    ))
+}
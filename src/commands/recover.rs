@@ -0,0 +1,63 @@
+// src/commands/recover.rs
+
+use crate::RecoverArgs;
+use anyhow::Result;
+use dlog::{Log, Store};
+
+pub fn handle_recover(args: &RecoverArgs, store: &mut Store) -> Result<()> {
+    // 1. 仅列出备份区的内容
+    if args.list {
+        let entries = store.backup_entries(None)?;
+        if entries.is_empty() {
+            println!("备份区为空。");
+            return Ok(());
+        }
+        let abbrevs = store.abbreviations()?;
+        println!("备份区中共有 {} 条日志 (最近移除的在前):", entries.len());
+        for log in &entries {
+            println!("  {}  {}", abbrevs.abbreviate(&log.get_hash()), log);
+            println!("           Dir: {}", log.directory);
+        }
+        return Ok(());
+    }
+
+    // 2. 确定需要恢复的条目：指定的短哈希，或最近移除的 N 条
+    let ids: Vec<i32> = if !args.identifiers.is_empty() {
+        let mut ids = Vec::new();
+        for identifier in &args.identifiers {
+            let id = store.resolve_backup(identifier)?;
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    } else {
+        store
+            .backup_entries(Some(args.num))?
+            .iter()
+            .map(|log| log.id)
+            .collect()
+    };
+
+    if ids.is_empty() {
+        println!("备份区为空，没有可以恢复的日志。");
+        return Ok(());
+    }
+
+    let restored_logs: Vec<Log> = store
+        .backup_entries(None)?
+        .into_iter()
+        .filter(|log| ids.contains(&log.id))
+        .collect();
+
+    // 3. 在一个事务中移回 logs 表
+    let restored = store.restore(&ids)?;
+
+    let abbrevs = store.abbreviations()?;
+    println!("✅ 已恢复 {} 条日志:", restored);
+    for log in &restored_logs {
+        println!("  {}  {}", abbrevs.abbreviate(&log.get_hash()), log);
+    }
+
+    Ok(())
+}
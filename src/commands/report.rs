@@ -0,0 +1,177 @@
+// src/commands/report.rs
+
+use crate::commands::format::{display_width, format_duration, pad, pad_left};
+use crate::commands::get::print_json;
+use crate::ReportArgs;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Days, Utc};
+use dlog::timeparse::{self, UserTimeZone};
+use dlog::{Query, Store};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// 一个标签或目录的用时
+#[derive(Debug, Serialize)]
+struct Row {
+    name: String,
+    seconds: u64,
+}
+
+/// `dlog report` 的完整结果，也是 JSON 输出的结构
+#[derive(Debug, Serialize)]
+struct Report {
+    since: String,
+    until: String,
+    /// 区间内有用时的计时条目数
+    entries: usize,
+    total_seconds: u64,
+    by_tag: Vec<Row>,
+    by_directory: Vec<Row>,
+}
+
+pub fn handle_report(args: &ReportArgs, store: &mut Store) -> Result<()> {
+    let tz = UserTimeZone::from_env()?;
+    let (since, until) = report_range(args, &tz)?;
+    let report = build_report(store, since, until)?;
+
+    match args.format.as_str() {
+        "json" => print_json(&report),
+        _ => print_table(&report, &tz, since, until),
+    }
+    Ok(())
+}
+
+/// 统计区间：默认为本周一 00:00 到当前时刻
+fn report_range(args: &ReportArgs, tz: &UserTimeZone) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    let now = Utc::now();
+    let today = tz.now().date_naive();
+
+    if args.today {
+        return Ok((tz.day_bounds(today)?.0, now));
+    }
+    if args.since.is_some() || args.until.is_some() {
+        let since = match &args.since {
+            Some(since) => timeparse::parse_time_spec(since, tz)?.as_lower_bound(),
+            None => DateTime::<Utc>::MIN_UTC,
+        };
+        let until = match &args.until {
+            Some(until) => timeparse::parse_time_spec(until, tz)?.as_upper_bound(),
+            None => now,
+        };
+        if since >= until {
+            bail!("统计区间为空: --since 需要早于 --until");
+        }
+        return Ok((since, until));
+    }
+
+    let monday = today - Days::new(today.weekday().num_days_from_monday().into());
+    Ok((tz.day_bounds(monday)?.0, now))
+}
+
+/// 汇总与区间重叠的计时条目，跨越边界的条目只计算区间内的部分
+fn build_report(store: &Store, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Report> {
+    // 区间开始前开始的条目也可能延续到区间内，因此只限制开始时间的上界。
+    // 数据库中的时间按秒比较，统计到当前时刻时不加限制，以免漏掉刚开始的条目
+    let now = Utc::now();
+    let mut query = Query::new().clock(true);
+    if until < now {
+        query = query.until(until);
+    }
+    let logs = store.find(&query)?;
+
+    let mut entries = 0;
+    let mut total = Duration::ZERO;
+    let mut by_tag: HashMap<String, Duration> = HashMap::new();
+    let mut by_directory: HashMap<String, Duration> = HashMap::new();
+    for log in &logs {
+        let Some((start, end)) = log.clock_span() else {
+            continue;
+        };
+        let start = start.max(since);
+        let end = end.unwrap_or(now).min(until);
+        let Ok(spent) = (end - start).to_std() else {
+            continue;
+        };
+        if spent.is_zero() {
+            continue;
+        }
+
+        entries += 1;
+        total += spent;
+        // 带有多个标签的条目计入每一个标签
+        let tags: Vec<&str> = match log.tags.as_deref() {
+            Some(tags) if !tags.is_empty() => tags.split(',').collect(),
+            _ => vec!["(无标签)"],
+        };
+        for tag in tags {
+            *by_tag.entry(tag.to_string()).or_default() += spent;
+        }
+        *by_directory.entry(log.directory.clone()).or_default() += spent;
+    }
+
+    Ok(Report {
+        since: since.to_rfc3339(),
+        until: until.to_rfc3339(),
+        entries,
+        total_seconds: total.as_secs(),
+        by_tag: sorted_rows(by_tag),
+        by_directory: sorted_rows(by_directory),
+    })
+}
+
+/// 按用时从多到少排列
+fn sorted_rows(totals: HashMap<String, Duration>) -> Vec<Row> {
+    let mut rows: Vec<Row> = totals
+        .into_iter()
+        .map(|(name, spent)| Row {
+            name,
+            seconds: spent.as_secs(),
+        })
+        .collect();
+    rows.sort_by(|a, b| b.seconds.cmp(&a.seconds).then_with(|| a.name.cmp(&b.name)));
+    rows
+}
+
+fn print_table(report: &Report, tz: &UserTimeZone, since: DateTime<Utc>, until: DateTime<Utc>) {
+    let since = if since == DateTime::<Utc>::MIN_UTC {
+        "最早".to_string()
+    } else {
+        tz.convert(since).format("%Y-%m-%d %H:%M").to_string()
+    };
+    println!(
+        "用时报告: {} → {}，共 {} ({} 条计时)",
+        since,
+        tz.convert(until).format("%Y-%m-%d %H:%M"),
+        format_duration(Duration::from_secs(report.total_seconds)),
+        report.entries
+    );
+    if report.entries == 0 {
+        return;
+    }
+
+    for (title, rows) in [("标签", &report.by_tag), ("目录", &report.by_directory)] {
+        println!();
+        let width = rows
+            .iter()
+            .map(|row| display_width(&row.name))
+            .max()
+            .unwrap_or(0)
+            .max(display_width(title));
+        println!(
+            "  {}  {}  {}",
+            pad(title, width),
+            pad_left("用时", 8),
+            pad_left("占比", 6)
+        );
+        for row in rows {
+            let percent = row.seconds as f64 * 100.0 / report.total_seconds.max(1) as f64;
+            println!(
+                "  {}  {}  {:>5.1}%",
+                pad(&row.name, width),
+                pad_left(&format_duration(Duration::from_secs(row.seconds)), 8),
+                percent
+            );
+        }
+    }
+}
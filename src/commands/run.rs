@@ -0,0 +1,157 @@
+// src/commands/run.rs
+
+use crate::commands::format::format_duration;
+use crate::commands::log::{keep_last_lines, prepare_new_log};
+use crate::RunArgs;
+use anyhow::{Context, Result};
+use dlog::{Level, NewLog, Store};
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+/// 运行期间最多保留的输出字节数，只需要足够截取最后几行
+const KEEP_OUTPUT_BYTES: usize = 64 * 1024;
+
+/// 运行一条命令，同时将其输出原样转发到终端，结束后记录一条日志。
+///
+/// 返回命令的退出码，dlog 以同样的退出码退出，不影响在脚本中使用。
+pub fn handle_run(args: &RunArgs, store: &mut Store) -> Result<i32> {
+    let command_line = quote_command(&args.command);
+
+    let started = Instant::now();
+    let mut child = Command::new(&args.command[0])
+        .args(&args.command[1..])
+        .stdin(Stdio::inherit())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("无法运行命令 {}", command_line))?;
+
+    // stdout 与 stderr 按到达的顺序写入同一个缓冲区，保留两者交错的样子
+    let output = Arc::new(Mutex::new(Vec::new()));
+    let readers = [
+        child
+            .stdout
+            .take()
+            .map(|stdout| tee(stdout, false, &output)),
+        child.stderr.take().map(|stderr| tee(stderr, true, &output)),
+    ];
+    let status = child.wait().context("等待命令结束时出错")?;
+    for reader in readers.into_iter().flatten() {
+        let _ = reader.join();
+    }
+    let elapsed = started.elapsed();
+
+    let output = output.lock().unwrap_or_else(|e| e.into_inner());
+    let tail = keep_last_lines(String::from_utf8_lossy(&output).trim_end(), args.tail);
+
+    let exit_code = status.code();
+    let outcome = match exit_code {
+        Some(code) => format!("退出状态 {}", code),
+        // Unix 上被信号终止的进程没有退出码
+        None => format!("异常终止 ({})", status),
+    };
+    let mut content = format!(
+        "$ {}\n{}，耗时 {}",
+        command_line,
+        outcome,
+        format_duration(elapsed)
+    );
+    if !tail.is_empty() {
+        content.push_str("\n\n");
+        content.push_str(&tail);
+    }
+
+    let mut new_log = prepare_new_log(store, content, args.tags.as_deref(), args.global)?;
+    // 自动添加的标签排在用户给出的标签之前
+    let mut tags = vec!["cmd".to_string()];
+    if !status.success() {
+        tags.push("failed".to_string());
+    }
+    tags.extend(
+        new_log
+            .tags
+            .drain(..)
+            .filter(|tag| tag != "cmd" && tag != "failed"),
+    );
+    new_log.tags = tags;
+    new_log.meta.insert("command".to_string(), command_line);
+    if let Some(code) = exit_code {
+        new_log
+            .meta
+            .insert("exit_code".to_string(), code.to_string());
+    }
+    new_log
+        .meta
+        .insert("duration_ms".to_string(), elapsed.as_millis().to_string());
+    let new_log = NewLog {
+        // 失败的命令默认记为 error，便于用 --level error 找出来
+        level: args.level.or((!status.success()).then_some(Level::Error)),
+        ..new_log
+    };
+    let log = store.insert(&new_log)?;
+
+    let hash = log.get_hash();
+    eprintln!(
+        "✅ 已记录命令 ({}，耗时 {})，唯一标识: {}",
+        outcome,
+        format_duration(elapsed),
+        store.abbreviations()?.abbreviate(&hash)
+    );
+
+    Ok(exit_code.unwrap_or(1))
+}
+
+/// 在后台线程中将子进程的一路输出转发到终端，并追加到共享的缓冲区。
+fn tee<R: Read + Send + 'static>(
+    mut source: R,
+    to_stderr: bool,
+    output: &Arc<Mutex<Vec<u8>>>,
+) -> thread::JoinHandle<()> {
+    let output = Arc::clone(output);
+    thread::spawn(move || {
+        let mut chunk = [0u8; 8192];
+        loop {
+            let n = match source.read(&mut chunk) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            };
+            // 终端写入失败 (如下游管道已关闭) 时仍然继续收集输出
+            let _ = if to_stderr {
+                io::stderr().write_all(&chunk[..n])
+            } else {
+                io::stdout()
+                    .write_all(&chunk[..n])
+                    .and_then(|_| io::stdout().flush())
+            };
+
+            let mut buffer = output.lock().unwrap_or_else(|e| e.into_inner());
+            buffer.extend_from_slice(&chunk[..n]);
+            if buffer.len() > KEEP_OUTPUT_BYTES {
+                let excess = buffer.len() - KEEP_OUTPUT_BYTES;
+                buffer.drain(..excess);
+            }
+        }
+    })
+}
+
+/// 将命令行还原为可以复制到 shell 中执行的形式
+fn quote_command(command: &[String]) -> String {
+    command
+        .iter()
+        .map(|arg| {
+            let plain = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+            if plain {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', r"'\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,164 @@
+// src/commands/session.rs
+
+use crate::commands::format::format_duration;
+use crate::commands::log::prepare_new_log;
+use crate::{SessionArgs, SessionCommands};
+use anyhow::{Context, Result};
+use dlog::location;
+use dlog::timeparse::UserTimeZone;
+use dlog::{Level, NewLog, Query, Store};
+use std::fs;
+use std::path::Path;
+
+/// shell 钩子加载后设置的环境变量，值为 shell 的种类
+pub(crate) const HOOK_ENV: &str = "DLOG_SHELL_HOOK";
+
+pub fn handle_session(args: &SessionArgs, store: &mut Store) -> Result<()> {
+    match &args.command {
+        SessionCommands::Start { name } => {
+            let session = store.start_session(name.as_deref())?;
+            write_marker(store)?;
+            println!("✅ 会话 {} 已开始", session);
+            if std::env::var_os(HOOK_ENV).is_none() {
+                println!("   当前 shell 尚未加载 dlog 钩子，命令不会被自动记录。");
+                println!(
+                    "   请在 shell 配置中加入 `eval \"$(dlog shell-init bash)\"` (zsh/fish 同理)。"
+                );
+            }
+        }
+        SessionCommands::Stop => {
+            // 会话开始后可能进入了另一个项目，此时结束标记中记下的数据库里的会话
+            let mut marked;
+            let store = match marked_store(store)? {
+                Some(other) => {
+                    marked = other;
+                    &mut marked
+                }
+                None => store,
+            };
+            let session = store.stop_session()?;
+            remove_marker(store)?;
+            let count = store.count(&Query::new().session(session.id))?;
+            println!(
+                "✅ 会话 {} 已结束，历时 {}，共记录 {} 条日志",
+                session,
+                format_duration(session.duration()),
+                count
+            );
+            println!("   使用 `dlog get --session {}` 查看", session.id);
+        }
+        SessionCommands::List => {
+            let sessions = store.sessions()?;
+            if sessions.is_empty() {
+                println!("还没有任何会话。");
+                return Ok(());
+            }
+            let tz = UserTimeZone::from_env()?;
+            for session in &sessions {
+                let state = if session.is_active() {
+                    "进行中".to_string()
+                } else {
+                    format!("历时 {}", format_duration(session.duration()))
+                };
+                println!(
+                    "{:<16} {}  {}  {} 条",
+                    session.to_string(),
+                    tz.display_rfc3339(&session.started_at),
+                    state,
+                    store.count(&Query::new().session(session.id))?
+                );
+            }
+        }
+        SessionCommands::Record {
+            exit_code,
+            duration,
+            command,
+        } => record_command(store, *exit_code, *duration, &command.join(" "))?,
+    }
+    Ok(())
+}
+
+/// 写入会话标记，记下数据库的绝对路径。
+///
+/// 钩子据此判断是否有进行中的会话，并通过 --db 把命令记录到开始会话时的数据库，
+/// 而不是命令所在目录对应的数据库。
+fn write_marker(store: &Store) -> Result<()> {
+    let marker = location::session_marker_path()?;
+    let db_path = store.path().canonicalize()?;
+    if let Some(dir) = marker.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&marker, format!("{}\n", db_path.display()))
+        .with_context(|| format!("无法写入会话标记 {}", marker.display()))
+}
+
+/// 当前数据库中没有进行中的会话，而会话标记指向另一个数据库时，打开那个数据库。
+fn marked_store(store: &Store) -> Result<Option<Store>> {
+    if store.active_session()?.is_some() {
+        return Ok(None);
+    }
+    let Ok(content) = fs::read_to_string(location::session_marker_path()?) else {
+        return Ok(None);
+    };
+    let path = Path::new(content.trim_end());
+    if !path.exists() || path == store.path().canonicalize()? {
+        return Ok(None);
+    }
+    let other = Store::open(path)?;
+    other.migrate()?;
+    Ok(Some(other))
+}
+
+/// 删除会话标记，标记属于其他数据库 (那里的会话在这之后开始) 时保留它。
+fn remove_marker(store: &Store) -> Result<()> {
+    let marker = location::session_marker_path()?;
+    let Ok(content) = fs::read_to_string(&marker) else {
+        return Ok(());
+    };
+    if Path::new(content.trim_end()) == store.path().canonicalize()? {
+        fs::remove_file(&marker)?;
+    }
+    Ok(())
+}
+
+/// 记录 shell 钩子报告的一条命令，没有进行中的会话时什么也不做。
+fn record_command(
+    store: &mut Store,
+    exit_code: i32,
+    duration: Option<u64>,
+    command: &str,
+) -> Result<()> {
+    let command = command.trim();
+    // dlog 自身的命令 (如 dlog log、dlog session stop) 已经以其他形式留下了记录
+    let program = command.split_whitespace().next().map(Path::new);
+    if program.is_none_or(|program| program.file_name() == Some("dlog".as_ref())) {
+        return Ok(());
+    }
+    if store.active_session()?.is_none() {
+        // 会话已在别处结束 (如直接修改了数据库)，清理过期的标记，钩子不再调用 dlog
+        return remove_marker(store);
+    }
+
+    let mut new_log = prepare_new_log(store, format!("$ {}", command), None, false)?;
+    new_log.tags = vec!["cmd".to_string()];
+    if exit_code != 0 {
+        new_log.tags.push("failed".to_string());
+    }
+    new_log
+        .meta
+        .insert("command".to_string(), command.to_string());
+    new_log
+        .meta
+        .insert("exit_code".to_string(), exit_code.to_string());
+    if let Some(duration) = duration {
+        new_log
+            .meta
+            .insert("duration_ms".to_string(), duration.to_string());
+    }
+    // 不受 log.default_level 影响；shell 中非零的退出码很常见，由 failed 标签标出即可
+    store.insert(&NewLog {
+        level: Some(Level::Info),
+        ..new_log
+    })?;
+    Ok(())
+}
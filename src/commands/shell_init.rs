@@ -0,0 +1,118 @@
+// src/commands/shell_init.rs
+
+use crate::commands::session::HOOK_ENV;
+use crate::ShellInitArgs;
+use anyhow::{Context, Result};
+use dlog::location;
+
+/// bash 钩子：DEBUG trap 记下命令开始的时刻，PROMPT_COMMAND 在命令结束后读取历史记录。
+///
+/// 历史编号没有变化时 (空行、以空格开头而未进入历史的命令) 不做记录。
+/// 会覆盖已有的 DEBUG trap。
+///
+/// 三种钩子都只在会话标记文件存在时才启动 dlog，并用 --db 指定标记中记下的数据库，
+/// 没有进行中的会话时每条命令只多一次文件检查。
+const BASH_HOOK: &str = r#"# dlog shell 钩子：会话进行期间 (dlog session start) 记录每一条命令
+export __HOOK_ENV__=bash
+__dlog_in_prompt=1
+
+__dlog_preexec() {
+    [ -n "$__dlog_in_prompt" ] && return
+    [ -n "$__dlog_start" ] && return
+    __dlog_start=${EPOCHREALTIME//[.,]/}
+    : "${__dlog_start:=$((SECONDS * 1000000))}"
+}
+
+__dlog_precmd() {
+    local exit_code=$?
+    __dlog_in_prompt=1
+    local num cmd db
+    read -r num cmd <<< "$(HISTTIMEFORMAT= builtin history 1)"
+    if [ -n "$__dlog_start" ] && [ -n "$cmd" ] && [ "$num" != "$__dlog_last_num" ] \
+        && [ -f __MARKER__ ] && read -r db < __MARKER__; then
+        local now=${EPOCHREALTIME//[.,]/}
+        : "${now:=$((SECONDS * 1000000))}"
+        ( __DLOG__ --db "$db" session record --exit-code "$exit_code" --duration "$(( (now - __dlog_start) / 1000 ))" -- "$cmd" >/dev/null 2>&1 & )
+    fi
+    __dlog_last_num=$num
+    __dlog_start=
+}
+
+__dlog_prompt_done() {
+    __dlog_in_prompt=
+}
+
+trap '__dlog_preexec' DEBUG
+PROMPT_COMMAND="__dlog_precmd;${PROMPT_COMMAND:+$PROMPT_COMMAND;}__dlog_prompt_done"
+"#;
+
+/// zsh 钩子：使用 preexec / precmd
+const ZSH_HOOK: &str = r#"# dlog shell 钩子：会话进行期间 (dlog session start) 记录每一条命令
+export __HOOK_ENV__=zsh
+zmodload zsh/datetime
+
+__dlog_preexec() {
+    __dlog_cmd=$1
+    __dlog_start=$EPOCHREALTIME
+}
+
+__dlog_precmd() {
+    local exit_code=$? cmd=$__dlog_cmd db
+    __dlog_cmd=
+    [[ -n $cmd && -f __MARKER__ ]] || return
+    read -r db < __MARKER__
+    local -i duration=$(( (EPOCHREALTIME - __dlog_start) * 1000 ))
+    ( __DLOG__ --db "$db" session record --exit-code $exit_code --duration $duration -- "$cmd" >/dev/null 2>&1 & )
+}
+
+autoload -Uz add-zsh-hook
+add-zsh-hook preexec __dlog_preexec
+add-zsh-hook precmd __dlog_precmd
+"#;
+
+/// fish 钩子：fish_postexec 事件直接提供命令行，耗时来自 $CMD_DURATION
+const FISH_HOOK: &str = r#"# dlog shell 钩子：会话进行期间 (dlog session start) 记录每一条命令
+set -gx __HOOK_ENV__ fish
+
+function __dlog_postexec --on-event fish_postexec
+    set -l exit_code $status
+    test -n "$argv[1]"; and test -f __MARKER__; or return
+    read -l db < __MARKER__
+    __DLOG__ --db "$db" session record --exit-code $exit_code --duration $CMD_DURATION -- $argv[1] >/dev/null 2>&1 &
+    disown 2>/dev/null
+end
+"#;
+
+pub fn handle_shell_init(args: &ShellInitArgs) -> Result<()> {
+    let marker = location::session_marker_path()?;
+    let marker = marker
+        .to_str()
+        .context("无法将会话标记的路径转换为字符串")?;
+    let hook = match args.shell.as_str() {
+        "bash" => BASH_HOOK,
+        "zsh" => ZSH_HOOK,
+        _ => FISH_HOOK,
+    };
+    // 使用当前程序的完整路径，钩子不依赖 PATH
+    let program = std::env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(|path| quote(path, &args.shell)))
+        .unwrap_or_else(|| "dlog".to_string());
+
+    print!(
+        "{}",
+        hook.replace("__HOOK_ENV__", HOOK_ENV)
+            .replace("__DLOG__", &program)
+            .replace("__MARKER__", &quote(marker, &args.shell))
+    );
+    Ok(())
+}
+
+/// 用单引号包裹路径，fish 与 POSIX shell 转义单引号的方式不同
+fn quote(path: &str, shell: &str) -> String {
+    if shell == "fish" {
+        format!("'{}'", path.replace('\\', r"\\").replace('\'', r"\'"))
+    } else {
+        format!("'{}'", path.replace('\'', r"'\''"))
+    }
+}
@@ -0,0 +1,355 @@
+// src/commands/stats.rs
+
+use crate::commands::format::{display_width, pad};
+use crate::commands::get::{filter_query, print_json, use_color};
+use crate::StatsArgs;
+use anyhow::Result;
+use chrono::{Datelike, Days, NaiveDate, Timelike, Utc};
+use dlog::timeparse::UserTimeZone;
+use dlog::{Log, Store};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// 按目录与标签统计时最多显示的行数
+const TOP_ROWS: usize = 10;
+
+/// 柱状图的最大宽度
+const BAR_WIDTH: usize = 30;
+
+const WEEKDAYS: [&str; 7] = ["周一", "周二", "周三", "周四", "周五", "周六", "周日"];
+
+/// 一个目录或标签的日志条数
+#[derive(Debug, Serialize)]
+struct Row {
+    name: String,
+    count: usize,
+}
+
+/// 一段连续有日志的日子
+#[derive(Debug, Clone, Copy, Serialize)]
+struct Streak {
+    days: usize,
+    start: NaiveDate,
+    end: NaiveDate,
+}
+
+/// `dlog stats` 的完整结果，也是 JSON 输出的结构
+#[derive(Debug, Serialize)]
+struct Stats {
+    total: usize,
+    first_day: Option<NaiveDate>,
+    last_day: Option<NaiveDate>,
+    /// 有日志的天数
+    active_days: usize,
+    /// 平均每条日志的字符数
+    average_length: f64,
+    longest_streak: Option<Streak>,
+    /// 截至今天 (今天还没有日志时截至昨天) 的连续天数
+    current_streak: usize,
+    by_directory: Vec<Row>,
+    by_tag: Vec<Row>,
+    /// 周一到周日
+    by_weekday: [usize; 7],
+    /// 0 点到 23 点
+    by_hour: [usize; 24],
+    by_day: BTreeMap<NaiveDate, usize>,
+}
+
+pub fn handle_stats(args: &StatsArgs, store: &mut Store) -> Result<()> {
+    let tz = UserTimeZone::from_env()?;
+    let logs = store.find(&filter_query(&args.filter, store)?)?;
+    let today = tz.now().date_naive();
+    let stats = collect(&logs, &tz, today);
+
+    match args.format.as_str() {
+        "json" => print_json(&stats),
+        _ => print_stats(&stats, today, args.weeks, use_color()),
+    }
+    Ok(())
+}
+
+fn collect(logs: &[Log], tz: &UserTimeZone, today: NaiveDate) -> Stats {
+    let mut by_directory: HashMap<&str, usize> = HashMap::new();
+    let mut by_tag: HashMap<&str, usize> = HashMap::new();
+    let mut by_weekday = [0; 7];
+    let mut by_hour = [0; 24];
+    let mut by_day: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+    let mut total_length = 0;
+
+    for log in logs {
+        *by_directory.entry(&log.directory).or_default() += 1;
+        match log.tags.as_deref() {
+            Some(tags) if !tags.is_empty() => {
+                for tag in tags.split(',') {
+                    *by_tag.entry(tag).or_default() += 1;
+                }
+            }
+            _ => *by_tag.entry("(无标签)").or_default() += 1,
+        }
+        total_length += log.content.chars().count();
+
+        // 星期、小时与日期都按阅读者的时区计算
+        if let Some(time) = log.parse_timestamp() {
+            let local = tz.convert(time.with_timezone(&Utc));
+            by_weekday[local.weekday().num_days_from_monday() as usize] += 1;
+            by_hour[local.hour() as usize] += 1;
+            *by_day.entry(local.date_naive()).or_default() += 1;
+        }
+    }
+
+    let (longest_streak, current_streak) = streaks(&by_day, today);
+    Stats {
+        total: logs.len(),
+        first_day: by_day.keys().next().copied(),
+        last_day: by_day.keys().next_back().copied(),
+        active_days: by_day.len(),
+        average_length: if logs.is_empty() {
+            0.0
+        } else {
+            total_length as f64 / logs.len() as f64
+        },
+        longest_streak,
+        current_streak,
+        by_directory: top_rows(by_directory),
+        by_tag: top_rows(by_tag),
+        by_weekday,
+        by_hour,
+        by_day,
+    }
+}
+
+/// 最长的连续天数，以及截至今天的连续天数
+fn streaks(by_day: &BTreeMap<NaiveDate, usize>, today: NaiveDate) -> (Option<Streak>, usize) {
+    let mut longest: Option<Streak> = None;
+    let mut current: Option<Streak> = None;
+    for &day in by_day.keys() {
+        current = match current {
+            Some(streak) if streak.end.succ_opt() == Some(day) => Some(Streak {
+                days: streak.days + 1,
+                end: day,
+                ..streak
+            }),
+            _ => Some(Streak {
+                days: 1,
+                start: day,
+                end: day,
+            }),
+        };
+        if longest.is_none_or(|longest| current.is_some_and(|c| c.days > longest.days)) {
+            longest = current;
+        }
+    }
+
+    // 今天还没有记录时，昨天为止的连续天数仍然有效
+    let yesterday = today.pred_opt();
+    let ongoing = current
+        .filter(|streak| streak.end == today || Some(streak.end) == yesterday)
+        .map_or(0, |streak| streak.days);
+    (longest, ongoing)
+}
+
+/// 按条数从多到少排列，只保留前 [`TOP_ROWS`] 行
+fn top_rows(counts: HashMap<&str, usize>) -> Vec<Row> {
+    let mut rows: Vec<Row> = counts
+        .into_iter()
+        .map(|(name, count)| Row {
+            name: name.to_string(),
+            count,
+        })
+        .collect();
+    rows.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+    rows.truncate(TOP_ROWS);
+    rows
+}
+
+fn print_stats(stats: &Stats, today: NaiveDate, weeks: u32, color: bool) {
+    let (Some(first), Some(last)) = (stats.first_day, stats.last_day) else {
+        println!("未找到匹配的日志。");
+        return;
+    };
+
+    println!(
+        "共 {} 条日志，{} → {}，{} 天有记录",
+        stats.total, first, last, stats.active_days
+    );
+    println!("平均每条 {:.1} 字", stats.average_length);
+    match stats.longest_streak {
+        Some(streak) => println!(
+            "连续记录: 当前 {} 天，最长 {} 天 ({} → {})",
+            stats.current_streak, streak.days, streak.start, streak.end
+        ),
+        None => println!("连续记录: 当前 {} 天", stats.current_streak),
+    }
+
+    println!();
+    println!("按目录");
+    print_bars(&stats.by_directory);
+
+    println!();
+    println!("按标签");
+    print_bars(&stats.by_tag);
+
+    println!();
+    println!("按星期");
+    let weekdays: Vec<Row> = WEEKDAYS
+        .iter()
+        .zip(stats.by_weekday)
+        .map(|(name, count)| Row {
+            name: name.to_string(),
+            count,
+        })
+        .collect();
+    print_bars(&weekdays);
+
+    println!();
+    println!("按小时");
+    println!("  {}", sparkline(&stats.by_hour));
+    println!("  0     6     12    18   23");
+
+    println!();
+    println!("最近 {} 周", weeks);
+    print_calendar(&stats.by_day, today, weeks, color);
+}
+
+/// 横向柱状图，名称按显示宽度对齐
+fn print_bars(rows: &[Row]) {
+    let max = rows.iter().map(|row| row.count).max().unwrap_or(0).max(1);
+    let width = rows
+        .iter()
+        .map(|row| display_width(&row.name))
+        .max()
+        .unwrap_or(0);
+    for row in rows {
+        let len = (row.count * BAR_WIDTH).div_ceil(max);
+        println!(
+            "  {}  {} {}",
+            pad(&row.name, width),
+            "█".repeat(len),
+            row.count
+        );
+    }
+}
+
+/// 用 ▁▂▃▄▅▆▇█ 表示各项的相对大小，为 0 的项留空
+fn sparkline(values: &[usize]) -> String {
+    const TICKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let max = values.iter().copied().max().unwrap_or(0).max(1);
+    values
+        .iter()
+        .map(|&value| match value {
+            0 => ' ',
+            _ => TICKS[(value * TICKS.len()).div_ceil(max) - 1],
+        })
+        .collect()
+}
+
+/// 类似 GitHub 贡献图的日历：每列一周 (周一开始)，每行一个星期几，颜色越深日志越多
+fn print_calendar(by_day: &BTreeMap<NaiveDate, usize>, today: NaiveDate, weeks: u32, color: bool) {
+    const SHADES: [&str; 5] = ["·", "░", "▒", "▓", "█"];
+    let this_monday = today
+        .checked_sub_days(Days::new(today.weekday().num_days_from_monday().into()))
+        .unwrap_or(today);
+    // 超出可表示范围的周 (只可能出现在日期的下限附近) 直接略去
+    let mondays: Vec<NaiveDate> = (0..weeks.max(1))
+        .rev()
+        .filter_map(|week| this_monday.checked_sub_days(Days::new(7 * u64::from(week))))
+        .collect();
+
+    // 在每个月第一次出现的那一列上方标出月份
+    let mut header = String::from("       ");
+    let mut previous_month = None;
+    for monday in &mondays {
+        let month = (monday.month() != previous_month.unwrap_or(0)).then(|| monday.month());
+        previous_month = Some(monday.month());
+        match month {
+            Some(month) => header.push_str(&format!("{:<2}", month)),
+            None => header.push_str("  "),
+        }
+    }
+    println!("{}", header.trim_end());
+
+    let max = by_day.values().copied().max().unwrap_or(0).max(1);
+    for (weekday, name) in WEEKDAYS.iter().enumerate() {
+        let mut line = format!("  {} ", name);
+        for monday in &mondays {
+            let day = *monday + Days::new(weekday as u64);
+            if day > today {
+                break;
+            }
+            let count = by_day.get(&day).copied().unwrap_or(0);
+            let level = match count {
+                0 => 0,
+                _ => (count * 4).div_ceil(max),
+            };
+            let cell = if color && level > 0 {
+                format!("\x1b[32m{}\x1b[0m", SHADES[level])
+            } else {
+                SHADES[level].to_string()
+            };
+            line.push_str(&cell);
+            line.push(' ');
+        }
+        println!("{}", line.trim_end());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(text: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(text, "%Y-%m-%d").unwrap()
+    }
+
+    fn days(dates: &[&str]) -> BTreeMap<NaiveDate, usize> {
+        dates.iter().map(|text| (date(text), 1)).collect()
+    }
+
+    fn span(streak: Option<Streak>) -> Option<(usize, NaiveDate, NaiveDate)> {
+        streak.map(|streak| (streak.days, streak.start, streak.end))
+    }
+
+    #[test]
+    fn longest_streak_crosses_month_and_year() {
+        let by_day = days(&[
+            "2024-12-30",
+            "2024-12-31",
+            "2025-01-01",
+            "2025-01-03",
+            "2025-02-28",
+            "2025-03-01",
+        ]);
+        let (longest, current) = streaks(&by_day, date("2025-06-01"));
+        assert_eq!(
+            span(longest),
+            Some((3, date("2024-12-30"), date("2025-01-01")))
+        );
+        assert_eq!(current, 0);
+    }
+
+    #[test]
+    fn earliest_of_equal_streaks_is_longest() {
+        let by_day = days(&["2025-01-01", "2025-01-02", "2025-01-05", "2025-01-06"]);
+        let (longest, _) = streaks(&by_day, date("2025-01-06"));
+        assert_eq!(
+            span(longest),
+            Some((2, date("2025-01-01"), date("2025-01-02")))
+        );
+    }
+
+    #[test]
+    fn current_streak_counts_until_today_or_yesterday() {
+        let by_day = days(&["2025-01-01", "2025-01-03", "2025-01-04", "2025-01-05"]);
+        assert_eq!(streaks(&by_day, date("2025-01-05")).1, 3);
+        // 今天还没有记录，昨天为止的连续天数仍然有效
+        assert_eq!(streaks(&by_day, date("2025-01-06")).1, 3);
+        assert_eq!(streaks(&by_day, date("2025-01-07")).1, 0);
+    }
+
+    #[test]
+    fn no_logs_means_no_streak() {
+        let (longest, current) = streaks(&BTreeMap::new(), date("2025-01-01"));
+        assert!(longest.is_none());
+        assert_eq!(current, 0);
+    }
+}
@@ -0,0 +1,76 @@
+// src/commands/tag.rs
+
+use crate::commands::pop::confirm;
+use crate::{TagArgs, TagCommands};
+use anyhow::Result;
+use dlog::tags::TagInfo;
+use dlog::Store;
+
+pub fn handle_tag(args: &TagArgs, store: &mut Store) -> Result<()> {
+    match &args.command {
+        TagCommands::List { tree } => {
+            let all_tags = store.tags()?;
+            if all_tags.is_empty() {
+                println!("还没有任何标签。");
+            } else if *tree {
+                print_tree(&all_tags, None, 0);
+            } else {
+                print_list(&all_tags);
+            }
+        }
+        TagCommands::Rename { old, new } => {
+            store.rename_tag(old, new)?;
+            println!(
+                "✅ 已将标签 '{}' 重命名为 '{}'，影响 {} 条日志。",
+                old,
+                new.trim(),
+                store.tag_usage(new)?
+            );
+        }
+        TagCommands::Merge { from, into } => {
+            let moved = store.merge_tags(from, into)?;
+            println!(
+                "✅ 已将标签 '{}' 合并到 '{}'，{} 条日志获得了新标签。",
+                from, into, moved
+            );
+        }
+        TagCommands::Move { tag, parent, .. } => {
+            store.move_tag(tag, parent.as_deref())?;
+            match parent {
+                Some(parent) => println!("✅ 标签 '{}' 现在位于 '{}' 之下。", tag, parent.trim()),
+                None => println!("✅ 标签 '{}' 现在是顶层标签。", tag),
+            }
+        }
+        TagCommands::Delete { name, yes } => {
+            let count = store.tag_usage(name)?;
+            println!("标签 '{}' 将被删除，并从 {} 条日志中移除。", name, count);
+            if !yes && !confirm("确认删除吗? [y/N] ")? {
+                println!("已取消操作。");
+                return Ok(());
+            }
+            let unlinked = store.delete_tag(name)?;
+            println!("✅ 已删除标签 '{}'，影响 {} 条日志。", name, unlinked);
+        }
+    }
+
+    Ok(())
+}
+
+/// 平铺模式：按使用次数从多到少排列
+fn print_list(all_tags: &[TagInfo]) {
+    let mut sorted: Vec<&TagInfo> = all_tags.iter().collect();
+    sorted.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.name.cmp(&b.name)));
+
+    let width = sorted.iter().map(|t| t.name.chars().count()).max().unwrap_or(0);
+    for tag in sorted {
+        println!("{:<width$}  {}", tag.name, tag.count, width = width);
+    }
+}
+
+/// 树状模式：子标签缩进显示在父标签之下
+fn print_tree(all_tags: &[TagInfo], parent_id: Option<i64>, depth: usize) {
+    for tag in all_tags.iter().filter(|t| t.parent_id == parent_id) {
+        println!("{}{} ({})", "  ".repeat(depth), tag.name, tag.count);
+        print_tree(all_tags, Some(tag.tag_id), depth + 1);
+    }
+}
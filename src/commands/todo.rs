@@ -0,0 +1,82 @@
+// src/commands/todo.rs
+
+use crate::commands::log::prepare_new_log;
+use crate::{TodoArgs, TodoCommands};
+use anyhow::Result;
+use dlog::timeparse::{self, UserTimeZone};
+use dlog::{Level, LevelFilter, NewLog, Query, Store};
+
+pub fn handle_todo(args: &TodoArgs, store: &mut Store) -> Result<()> {
+    match &args.command {
+        TodoCommands::Add {
+            message,
+            tags,
+            due,
+            global,
+        } => {
+            let tz = UserTimeZone::from_env()?;
+            let due = due
+                .as_deref()
+                .map(|due| timeparse::parse_due_date(due, &tz))
+                .transpose()?;
+            let new_log = NewLog {
+                level: Some(Level::Todo),
+                due,
+                ..prepare_new_log(store, message.clone(), tags.as_deref(), *global)?
+            };
+            let log = store.insert(&new_log)?;
+
+            let hash = log.get_hash();
+            println!("✅ 已添加待办事项 {}", store.abbreviations()?.abbreviate(&hash));
+            if let Some(due) = due {
+                println!("   截止日期: {}", due);
+            }
+        }
+        TodoCommands::List { all } => {
+            let mut query = Query::new().level(LevelFilter::Exact(Level::Todo));
+            if !all {
+                let current_dir = store.directory_key(&std::env::current_dir()?)?;
+                query = query
+                    .directory(current_dir)
+                    .recursive(true)
+                    .include_global(true);
+            }
+            let mut todos = store.find(&query.reverse(true))?;
+            if todos.is_empty() {
+                println!("没有未完成的待办事项。");
+                return Ok(());
+            }
+
+            // 有截止日期的排在前面，按日期从早到晚；其余保持记录的先后顺序
+            let due_of = |log: &dlog::Log| log.get_metadata().and_then(|m| m.due);
+            todos.sort_by_key(|log| {
+                let due = due_of(log);
+                (due.is_none(), due)
+            });
+
+            let today = UserTimeZone::from_env()?.now().date_naive().to_string();
+            let abbrevs = store.abbreviations()?;
+            for log in &todos {
+                let hash = log.get_hash();
+                let due = match due_of(log) {
+                    // YYYY-MM-DD 可以直接按字符串比较
+                    Some(due) if due < today => format!(" (已逾期 {})", due),
+                    Some(due) => format!(" (截止 {})", due),
+                    None => String::new(),
+                };
+                println!("  {}  {}{}", abbrevs.abbreviate(&hash), log.content, due);
+            }
+        }
+        TodoCommands::Done { identifiers } => {
+            let abbrevs = store.abbreviations()?;
+            for identifier in identifiers {
+                let id = store.resolve(identifier)?;
+                let log = store.mark_done(id)?;
+                let hash = log.get_hash();
+                println!("✅ 已完成 {}  {}", abbrevs.abbreviate(&hash), log.content);
+            }
+        }
+    }
+
+    Ok(())
+}
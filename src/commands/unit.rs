@@ -0,0 +1,53 @@
+// src/commands/unit.rs
+
+use crate::commands::format::format_duration;
+use crate::{UnitArgs, UnitCommands};
+use anyhow::Result;
+use dlog::timeparse::UserTimeZone;
+use dlog::{Query, Store};
+
+pub fn handle_unit(args: &UnitArgs, store: &mut Store) -> Result<()> {
+    match &args.command {
+        UnitCommands::Start { name } => {
+            let unit = store.start_unit(name.as_deref())?;
+            println!(
+                "✅ 时间单元 {} 已开始，之后记录的日志都会带有标记 {}",
+                unit, unit.mark
+            );
+        }
+        UnitCommands::Stop => {
+            let unit = store.stop_unit()?;
+            let count = store.count(&Query::new().unit(&unit.mark))?;
+            println!(
+                "✅ 时间单元 {} 已结束，历时 {}，共记录 {} 条日志",
+                unit,
+                format_duration(unit.duration()),
+                count
+            );
+            println!("   使用 `dlog get --unit {}` 查看", unit.mark);
+        }
+        UnitCommands::List => {
+            let units = store.units()?;
+            if units.is_empty() {
+                println!("还没有任何时间单元。");
+                return Ok(());
+            }
+            let tz = UserTimeZone::from_env()?;
+            for unit in &units {
+                let state = if unit.is_active() {
+                    "进行中".to_string()
+                } else {
+                    format!("历时 {}", format_duration(unit.duration()))
+                };
+                println!(
+                    "{:<24} {}  {}  {} 条",
+                    unit.to_string(),
+                    tz.display_rfc3339(&unit.started_at),
+                    state,
+                    store.count(&Query::new().unit(&unit.mark))?
+                );
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,90 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/config.rs
+
+//! 用户可以修改的配置项，保存在数据库的 `configs` 表中。
+//!
+//! 每个数据库 (日志本) 拥有各自的配置。`configs` 表同时保存 `db_version`
+//! 等内部数据，只有在 [`KEYS`] 中登记的配置项才允许用户读写。
+
+use crate::model::Level;
+use anyhow::{bail, Result};
+
+/// 一个可配置项。
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// 检查并规范化用户给出的值
+    pub normalize: fn(&str) -> Result<String>,
+}
+
+/// `dlog log` 未指定 `--level` 时使用的级别
+pub const DEFAULT_LEVEL: &str = "log.default_level";
+
+/// 为 true 时，`dlog log` 未提供 `-m` 时默认打开编辑器 (相当于 `-e`)
+pub const USE_EDITOR: &str = "log.use_editor";
+
+/// 为 true 时，在 git 工作区中记录日志时调用 `git status` 记下工作区是否有未提交的修改
+pub const GIT_RECORD_DIRTY: &str = "git.record_dirty";
+
+/// 所有可配置项
+pub const KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        name: DEFAULT_LEVEL,
+        description: "dlog log 未指定 --level 时使用的级别 (info/warn/error/todo/done)",
+        normalize: normalize_level,
+    },
+    ConfigKey {
+        name: USE_EDITOR,
+        description: "为 true 时，dlog log 未提供 -m 时默认使用 $VISUAL/$EDITOR 编辑 (true/false)",
+        normalize: normalize_bool,
+    },
+    ConfigKey {
+        name: GIT_RECORD_DIRTY,
+        description: "为 true 时，记录日志时调用 git status 记下工作区是否有未提交的修改，大仓库中会变慢 (true/false)",
+        normalize: normalize_bool,
+    },
+];
+
+/// 查找一个配置项，不存在时返回带有可选项的错误。
+pub fn find_key(name: &str) -> Result<&'static ConfigKey> {
+    match KEYS.iter().find(|key| key.name == name) {
+        Some(key) => Ok(key),
+        None => {
+            let names: Vec<&str> = KEYS.iter().map(|key| key.name).collect();
+            bail!("未知的配置项 '{}'，可选: {}", name, names.join(", "))
+        }
+    }
+}
+
+/// 读取布尔型配置项的值
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value.trim().to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Some(true),
+        "false" | "no" | "off" | "0" => Some(false),
+        _ => None,
+    }
+}
+
+fn normalize_bool(value: &str) -> Result<String> {
+    match parse_bool(value) {
+        Some(value) => Ok(value.to_string()),
+        None => bail!("'{}' 不是有效的布尔值，可选: true, false", value),
+    }
+}
+
+fn normalize_level(value: &str) -> Result<String> {
+    let level: Level = value.parse().map_err(anyhow::Error::msg)?;
+    Ok(level.as_str().to_string())
+}
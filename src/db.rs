@@ -13,6 +13,8 @@
 // limitations under the License.
 // src/db.rs
 
+use crate::ident;
+use crate::tags;
 use chrono::Local;
 use rusqlite::ffi::ErrorCode;
 use rusqlite::{Connection, Result, Transaction};
@@ -53,7 +55,7 @@ fn backup_database(db_path: &Path) -> std::io::Result<PathBuf> {
 pub const MAX_BACKUP_ENTRIES: usize = 100;
 
 // 定义程序所使用的数据库版本（并不是程序版本），用于版本升级时对数据库进行操作
-pub const DLOG_DB_VERSION: u32 = 2;
+pub const DLOG_DB_VERSION: u32 = 10;
 
 /**
  * 检查 dlog 所使用的数据库模式的版本。
@@ -120,11 +122,8 @@ fn migrate_to_v2(tx: &Transaction) -> Result<()> {
     println!("     - Table 'configs' created.");
 
     // 步骤 3: 插入或更新数据库版本号为 2
-    tx.execute(
-        "INSERT OR REPLACE INTO configs (key, value) VALUES ('db_version', ?1)",
-        [&DLOG_DB_VERSION.to_string()],
-    )?;
-    println!("     - Database version set to {}.", DLOG_DB_VERSION);
+    set_db_version(tx, 2)?;
+    println!("     - Database version set to 2.");
 
     // 4. 创建触发器，用于在 backup 表条目过多时自动清理
     let trigger_sql = format!(
@@ -143,6 +142,389 @@ fn migrate_to_v2(tx: &Transaction) -> Result<()> {
     Ok(())
 }
 
+/// 执行从数据库版本 2 到 3 的迁移：标签系统关系化。
+///
+/// 此函数在一个现有的事务中运行，以确保原子性。
+/// 它会：
+/// 1. 创建 `tags` 与 `log_tags` 表。
+/// 2. 将 `logs.tags` 中逗号分隔的标签拆分写入新表。
+/// 3. 将 `backup.tags` 中出现的标签名登记到 `tags` 表 (backup 仍保留字符串快照)。
+/// 4. 移除 `logs.tags` 字段。
+/// 5. 在 `configs` 表中将 `db_version` 设置为 3
+fn migrate_to_v3(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 3...");
+
+    // 步骤 1: 创建标签相关的表
+    create_tag_tables(tx)?;
+    println!("     - Tables 'tags' and 'log_tags' created.");
+
+    // 步骤 2: 拆分 logs.tags
+    let tagged_logs: Vec<(i64, String)> = {
+        let mut stmt =
+            tx.prepare("SELECT id, tags FROM logs WHERE tags IS NOT NULL AND tags != ''")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_>>()?
+    };
+    for (log_id, tags) in &tagged_logs {
+        tags::set_log_tags(tx, *log_id, &tags::parse_tag_list(tags))?;
+    }
+    println!("     - Tags of {} logs migrated.", tagged_logs.len());
+
+    // 步骤 3: 登记 backup.tags 中的标签名
+    let backup_tags: Vec<String> = {
+        let mut stmt =
+            tx.prepare("SELECT tags FROM backup WHERE tags IS NOT NULL AND tags != ''")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        rows.collect::<Result<_>>()?
+    };
+    for tags in &backup_tags {
+        for tag in tags::parse_tag_list(tags) {
+            tags::ensure_tag(tx, &tag)?;
+        }
+    }
+
+    // 步骤 4: logs 表不再需要 tags 字段
+    tx.execute("ALTER TABLE logs DROP COLUMN tags", ())?;
+    println!("     - Column 'logs.tags' dropped.");
+
+    // 步骤 5: 更新数据库版本号为 3
+    set_db_version(tx, 3)?;
+    println!("     - Database version set to 3.");
+
+    Ok(())
+}
+
+/// 创建 `tags` 与 `log_tags` 表 (数据库版本 3 引入)。
+fn create_tag_tables(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS tags (
+            tag_id      INTEGER PRIMARY KEY,
+            name        TEXT NOT NULL UNIQUE,
+            parent_id   INTEGER,
+            FOREIGN KEY (parent_id) REFERENCES tags(tag_id)
+        )",
+        (),
+    )?;
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS log_tags (
+            log_id      INTEGER NOT NULL,
+            tag_id      INTEGER NOT NULL,
+            PRIMARY KEY (log_id, tag_id),
+            FOREIGN KEY (log_id) REFERENCES logs(id) ON DELETE CASCADE,
+            FOREIGN KEY (tag_id) REFERENCES tags(tag_id) ON DELETE CASCADE
+        )",
+        (),
+    )?;
+    conn.execute(
+        "CREATE INDEX IF NOT EXISTS idx_log_tags_tag_id ON log_tags(tag_id)",
+        (),
+    )?;
+    Ok(())
+}
+
+/// 执行从数据库版本 3 到 4 的迁移：全文检索。
+///
+/// 此函数在一个现有的事务中运行，以确保原子性。
+/// 它会：
+/// 1. 创建 FTS5 虚拟表 `logs_fts` 以及与 `logs` 保持同步的触发器。
+/// 2. 为已有的日志建立索引。
+/// 3. 在 `configs` 表中将 `db_version` 设置为 4
+fn migrate_to_v4(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 4...");
+
+    // 步骤 1: 创建全文检索表与触发器
+    create_search_index(tx)?;
+    println!("     - Virtual table 'logs_fts' created.");
+
+    // 步骤 2: 为已有的日志建立索引
+    tx.execute("INSERT INTO logs_fts(logs_fts) VALUES ('rebuild')", ())?;
+    println!("     - Existing logs indexed.");
+
+    // 步骤 3: 更新数据库版本号为 4
+    set_db_version(tx, 4)?;
+    println!("     - Database version set to 4.");
+
+    Ok(())
+}
+
+/// 执行从数据库版本 4 到 5 的迁移：记录书写者的时区。
+///
+/// 此函数在一个现有的事务中运行，以确保原子性。
+/// 它会：
+/// 1. 为 `logs` 与 `backup` 表添加 `utc_offset` 字段 (书写时相对 UTC 的秒数)。
+///    已有的日志无法得知原始时区，该字段保持为 NULL。
+/// 2. 在 `configs` 表中将 `db_version` 设置为 5
+fn migrate_to_v5(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 5...");
+
+    // 步骤 1: 添加 utc_offset 字段
+    tx.execute("ALTER TABLE logs ADD COLUMN utc_offset INTEGER", ())?;
+    tx.execute("ALTER TABLE backup ADD COLUMN utc_offset INTEGER", ())?;
+    println!("     - Column 'utc_offset' added to 'logs' and 'backup'.");
+
+    // 步骤 2: 更新数据库版本号为 5
+    set_db_version(tx, 5)?;
+    println!("     - Database version set to 5.");
+
+    Ok(())
+}
+
+/**
+ * 从版本 5 迁移到版本 6
+ *
+ * - `metadata` 从单纯的哈希字符串改为 JSON 对象 `{"id": "<hash>", ...}`，结构见 `model::Metadata`
+ * - 没有 metadata 的旧日志以十六进制的 id 作为标识符
+ */
+fn migrate_to_v6(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 6...");
+
+    // 步骤 1: 将 logs 与 backup 中的 metadata 包装为 JSON 对象
+    for table in ["logs", "backup"] {
+        let converted = tx.execute(
+            &format!(
+                "UPDATE {} SET metadata = json_object('id', coalesce(metadata, printf('%x', id)))
+                 WHERE metadata IS NULL OR substr(metadata, 1, 1) != '{{'",
+                table
+            ),
+            (),
+        )?;
+        println!("     - {} rows in '{}' converted to JSON metadata.", converted, table);
+    }
+
+    // 步骤 2: 更新数据库版本号为 6
+    set_db_version(tx, 6)?;
+    println!("     - Database version set to 6.");
+
+    Ok(())
+}
+
+/**
+ * 从版本 6 迁移到版本 7
+ *
+ * - 标识符改为 `timestamp`、`directory`、`content` 的 SHA-1，见 `ident::log_hash`
+ * - 旧标识符记录在 `id_aliases` 表中，用户仍然可以用它们定位日志
+ * - 为 logs 中的标识符建立唯一索引
+ */
+fn migrate_to_v7(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 7...");
+
+    // 步骤 1: 创建别名表
+    create_id_aliases(tx)?;
+    println!("     - Table 'id_aliases' created.");
+
+    // 步骤 2: 重新计算 logs 与 backup 中的标识符
+    for table in ["logs", "backup"] {
+        let rows: Vec<(i64, String, String, String, String)> = {
+            let mut stmt = tx.prepare(&format!(
+                "SELECT id, timestamp, directory, content, json_extract(metadata, '$.id') FROM {}",
+                table
+            ))?;
+            let rows = stmt.query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?))
+            })?;
+            rows.collect::<Result<_>>()?
+        };
+
+        let mut used = std::collections::HashSet::new();
+        for (id, timestamp, directory, content, old_hash) in &rows {
+            let new_hash = ident::log_hash(timestamp, directory, content);
+            // 时间与内容完全相同的日志保留原标识符，以免违反唯一索引
+            if !used.insert(new_hash.clone()) {
+                continue;
+            }
+            tx.execute(
+                &format!(
+                    "UPDATE {} SET metadata = json_set(metadata, '$.id', ?1) WHERE id = ?2",
+                    table
+                ),
+                rusqlite::params![new_hash, id],
+            )?;
+            tx.execute(
+                "INSERT OR IGNORE INTO id_aliases (alias, hash) VALUES (?1, ?2)",
+                rusqlite::params![old_hash, new_hash],
+            )?;
+        }
+        println!("     - {} identifiers in '{}' rewritten.", rows.len(), table);
+    }
+
+    // 步骤 3: 建立唯一索引
+    create_id_index(tx)?;
+    println!("     - Unique index on log identifiers created.");
+
+    // 步骤 4: 更新数据库版本号为 7
+    set_db_version(tx, 7)?;
+    println!("     - Database version set to 7.");
+
+    Ok(())
+}
+/**
+ * 从版本 7 迁移到版本 8
+ *
+ * - 新增 `sessions` 表，记录 `dlog session start/stop` 的会话
+ * - 会话中记录的日志在 metadata 的 `session` 字段中保存会话编号，无需修改已有数据
+ */
+fn migrate_to_v8(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 8...");
+
+    // 步骤 1: 创建会话表
+    create_sessions_table(tx)?;
+    println!("     - Table 'sessions' created.");
+
+    // 步骤 2: 更新数据库版本号为 8
+    set_db_version(tx, 8)?;
+    println!("     - Database version set to 8.");
+
+    Ok(())
+}
+
+/// 创建 `sessions` 表 (数据库版本 8 引入)。
+fn create_sessions_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            id          INTEGER PRIMARY KEY,
+            name        TEXT,
+            started_at  TEXT NOT NULL,
+            ended_at    TEXT -- 进行中的会话为 NULL
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/**
+ * 从版本 8 迁移到版本 9
+ *
+ * - 新增 `units` 表，记录 `dlog unit start/stop` 的时间单元
+ * - 时间单元中记录的日志在 metadata 的 `unit` 字段中保存标记，无需修改已有数据
+ */
+fn migrate_to_v9(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 9...");
+
+    // 步骤 1: 创建时间单元表
+    create_units_table(tx)?;
+    println!("     - Table 'units' created.");
+
+    // 步骤 2: 更新数据库版本号为 9
+    set_db_version(tx, 9)?;
+    println!("     - Database version set to 9.");
+
+    Ok(())
+}
+
+/**
+ * 从版本 9 迁移到版本 10
+ *
+ * - 备份区的清理触发器改为按 `backup.id` (即移除的先后) 淘汰最早移除的条目。
+ *   旧触发器按日志原本的时间戳淘汰，备份区满时刚移除的旧日志会被立即清理，无法恢复
+ */
+fn migrate_to_v10(tx: &Transaction) -> Result<()> {
+    println!("  -> Migrating to version 10...");
+
+    // 步骤 1: 重建备份区的清理触发器
+    tx.execute("DROP TRIGGER IF EXISTS trim_backup_logs", ())?;
+    create_backup_trim_trigger(tx)?;
+    println!("     - Trigger 'trim_backup_logs' now evicts by removal order.");
+
+    // 步骤 2: 更新数据库版本号为 10
+    set_db_version(tx, 10)?;
+    println!("     - Database version set to 10.");
+
+    Ok(())
+}
+
+/// 创建备份区的清理触发器 (数据库版本 10 起按移除的先后淘汰)。
+///
+/// 条目超过 [`MAX_BACKUP_ENTRIES`] 时删除 `id` 最小，即最早移除的条目。
+fn create_backup_trim_trigger(conn: &Connection) -> Result<()> {
+    conn.execute(
+        &format!(
+            "CREATE TRIGGER IF NOT EXISTS trim_backup_logs
+             AFTER INSERT ON backup
+             WHEN (SELECT COUNT(*) FROM backup) > {limit}
+             BEGIN
+                 DELETE FROM backup WHERE id IN (
+                     SELECT id FROM backup ORDER BY id ASC LIMIT (SELECT COUNT(*) - {limit} FROM backup)
+                 );
+             END;",
+            limit = MAX_BACKUP_ENTRIES
+        ),
+        (),
+    )?;
+    Ok(())
+}
+
+/// 创建 `units` 表 (数据库版本 9 引入)。
+fn create_units_table(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS units (
+            mark        TEXT PRIMARY KEY, -- 3 位的标记
+            name        TEXT,
+            started_at  TEXT NOT NULL,
+            ended_at    TEXT -- 进行中的时间单元为 NULL
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// 创建 `id_aliases` 表 (数据库版本 7 引入)，保存旧标识符到新标识符的映射。
+fn create_id_aliases(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS id_aliases (
+            alias TEXT PRIMARY KEY,
+            hash  TEXT NOT NULL
+        )",
+        (),
+    )?;
+    Ok(())
+}
+
+/// 为 logs 中的标识符建立唯一索引 (数据库版本 7 引入)。
+fn create_id_index(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE UNIQUE INDEX IF NOT EXISTS logs_hash ON logs (json_extract(metadata, '$.id'))",
+        (),
+    )?;
+    Ok(())
+}
+
+/// 创建 `logs_fts` 全文检索表 (数据库版本 4 引入)。
+///
+/// `logs_fts` 是 `logs.content` 的外部内容索引，本身不保存日志内容，
+/// 由 INSERT/DELETE/UPDATE 触发器保持与 `logs` 同步。
+fn create_search_index(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS logs_fts USING fts5(
+            content,
+            content = 'logs',
+            content_rowid = 'id',
+            tokenize = 'unicode61'
+        );
+
+        CREATE TRIGGER IF NOT EXISTS logs_fts_insert AFTER INSERT ON logs BEGIN
+            INSERT INTO logs_fts (rowid, content) VALUES (new.id, new.content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS logs_fts_delete AFTER DELETE ON logs BEGIN
+            INSERT INTO logs_fts (logs_fts, rowid, content) VALUES ('delete', old.id, old.content);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS logs_fts_update AFTER UPDATE OF content ON logs BEGIN
+            INSERT INTO logs_fts (logs_fts, rowid, content) VALUES ('delete', old.id, old.content);
+            INSERT INTO logs_fts (rowid, content) VALUES (new.id, new.content);
+        END;",
+    )
+}
+
+/// 在 `configs` 表中记录数据库版本号。
+fn set_db_version(conn: &Connection, version: u32) -> Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO configs (key, value) VALUES ('db_version', ?1)",
+        [&version.to_string()],
+    )?;
+    Ok(())
+}
+
 /// 数据库版本更新的主函数
 pub fn run_migrations(db_path: &PathBuf) -> Result<()> {
     if !db_path.exists() {
@@ -187,8 +569,30 @@ pub fn run_migrations(db_path: &PathBuf) -> Result<()> {
             // 将事务传递给特定的迁移函数
             migrate_to_v2(&tx)?;
         }
-        // 如果未來有 v3, 在這裡加:
-        // if current_db_version < 3 { migrate_to_v3(&tx)?; ... }
+        if current_db_version < 3 {
+            migrate_to_v3(&tx)?;
+        }
+        if current_db_version < 4 {
+            migrate_to_v4(&tx)?;
+        }
+        if current_db_version < 5 {
+            migrate_to_v5(&tx)?;
+        }
+        if current_db_version < 6 {
+            migrate_to_v6(&tx)?;
+        }
+        if current_db_version < 7 {
+            migrate_to_v7(&tx)?;
+        }
+        if current_db_version < 8 {
+            migrate_to_v8(&tx)?;
+        }
+        if current_db_version < 9 {
+            migrate_to_v9(&tx)?;
+        }
+        if current_db_version < 10 {
+            migrate_to_v10(&tx)?;
+        }
 
         tx.commit()?;
         println!("🎉 数据库升级成功！");
@@ -222,14 +626,14 @@ pub fn initialize_db(db_path: &Path) -> Result<()> {
             timestamp   TEXT NOT NULL,
             directory   TEXT NOT NULL,
             content     TEXT NOT NULL,
-            tags        TEXT,
-            metadata    TEXT, -- 用于存储哈希标识符
-            level       TEXT
+            metadata    TEXT, -- JSON 对象，包含哈希标识符 id 等上下文
+            level       TEXT,
+            utc_offset  INTEGER -- 书写时相对 UTC 的秒数
         )",
         (),
     )?;
 
-    // 2. 创建结构相同的 backup 表
+    // 2. 创建 backup 表，标签以逗号分隔的字符串快照保存
     conn.execute(
         "CREATE TABLE IF NOT EXISTS backup (
             id          INTEGER PRIMARY KEY,
@@ -238,7 +642,8 @@ pub fn initialize_db(db_path: &Path) -> Result<()> {
             content     TEXT NOT NULL,
             tags        TEXT,
             metadata    TEXT,
-            level       TEXT
+            level       TEXT,
+            utc_offset  INTEGER
         )",
         (),
     )?;
@@ -253,18 +658,325 @@ pub fn initialize_db(db_path: &Path) -> Result<()> {
     )?;
 
     // 4. 创建触发器，用于在 backup 表条目过多时自动清理
-    let trigger_sql = format!(
-        "CREATE TRIGGER IF NOT EXISTS trim_backup_logs
-         AFTER INSERT ON backup
-         WHEN (SELECT COUNT(*) FROM backup) > {limit}
-         BEGIN
-             DELETE FROM backup WHERE id IN (
-                 SELECT id FROM backup ORDER BY timestamp ASC LIMIT (SELECT COUNT(*) - {limit} FROM backup)
-             );
-         END;",
-        limit = MAX_BACKUP_ENTRIES
-    );
-    conn.execute(&trigger_sql, ())?;
+    create_backup_trim_trigger(&conn)?;
+
+    // 5. 创建标签相关的表
+    create_tag_tables(&conn)?;
+
+    // 6. 创建全文检索索引
+    create_search_index(&conn)?;
+
+    // 7. 创建标识符的唯一索引与别名表
+    create_id_index(&conn)?;
+    create_id_aliases(&conn)?;
+
+    // 8. 创建会话表与时间单元表
+    create_sessions_table(&conn)?;
+    create_units_table(&conn)?;
+
+    // 9. 记录数据库版本，新建的数据库无需再执行迁移
+    set_db_version(&conn, DLOG_DB_VERSION)?;
 
     Ok(())
 }
+
+/// 将指定 id 的日志从 `logs` 表移动到 `backup` 表。
+///
+/// 调用者需要提供一个事务，复制与删除将在同一个事务中完成。
+/// 备份表中的行会获得新的 id，因此 `backup.id` 越大表示越晚被移除。
+///
+/// # Returns
+/// 成功时返回被移动的日志条数。
+pub fn move_logs_to_backup(tx: &Transaction, ids: &[i32]) -> Result<usize> {
+    if ids.is_empty() {
+        return Ok(0);
+    }
+
+    let placeholders = vec!["?"; ids.len()].join(",");
+    let params = rusqlite::params_from_iter(ids.iter());
+
+    tx.execute(
+        &format!(
+            "INSERT INTO backup (timestamp, directory, content, tags, metadata, level, utc_offset)
+             SELECT timestamp, directory, content, {}, metadata, level, utc_offset
+             FROM logs WHERE id IN ({}) ORDER BY timestamp ASC",
+            tags::LOGS_TAGS_COLUMN,
+            placeholders
+        ),
+        params,
+    )?;
+
+    // 不依赖 PRAGMA foreign_keys，显式清理关联记录，避免 id 被复用时标签错挂
+    tx.execute(
+        &format!("DELETE FROM log_tags WHERE log_id IN ({})", placeholders),
+        rusqlite::params_from_iter(ids.iter()),
+    )?;
+
+    let moved = tx.execute(
+        &format!("DELETE FROM logs WHERE id IN ({})", placeholders),
+        rusqlite::params_from_iter(ids.iter()),
+    )?;
+
+    Ok(moved)
+}
+
+/// 将指定 id 的条目从 `backup` 表移回 `logs` 表。
+///
+/// 原始的时间戳、目录、标签与 metadata (哈希标识符) 都会被完整保留。
+///
+/// # Returns
+/// 成功时返回被恢复的日志条数。
+pub fn restore_logs_from_backup(tx: &Transaction, backup_ids: &[i32]) -> Result<usize> {
+    if backup_ids.is_empty() {
+        return Ok(0);
+    }
+
+    let mut restored = 0;
+    for backup_id in backup_ids {
+        let tags: Option<String> = tx.query_row(
+            "SELECT tags FROM backup WHERE id = ?1",
+            [backup_id],
+            |row| row.get(0),
+        )?;
+
+        tx.execute(
+            "INSERT INTO logs (timestamp, directory, content, metadata, level, utc_offset)
+             SELECT timestamp, directory, content, metadata, level, utc_offset
+             FROM backup WHERE id = ?1",
+            [backup_id],
+        )?;
+        let log_id = tx.last_insert_rowid();
+        tags::set_log_tags(tx, log_id, &tags::parse_tag_list(&tags.unwrap_or_default()))?;
+
+        restored += tx.execute("DELETE FROM backup WHERE id = ?1", [backup_id])?;
+    }
+
+    Ok(restored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_db_path;
+    use tempfile::TempDir;
+
+    /// 按版本 2 的表结构创建数据库并升级到当前版本。
+    ///
+    /// 版本 2 中标签是逗号分隔的字符串，metadata 是旧的哈希字符串或 NULL
+    fn migrated_v2_fixture() -> (TempDir, Connection) {
+        let (dir, path) = temp_db_path();
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE logs (
+                id          INTEGER PRIMARY KEY,
+                timestamp   TEXT NOT NULL,
+                directory   TEXT NOT NULL,
+                content     TEXT NOT NULL,
+                tags        TEXT,
+                metadata    TEXT,
+                level       TEXT
+            );
+            CREATE TABLE backup (
+                id          INTEGER PRIMARY KEY,
+                timestamp   TEXT NOT NULL,
+                directory   TEXT NOT NULL,
+                content     TEXT NOT NULL,
+                tags        TEXT,
+                metadata    TEXT,
+                level       TEXT
+            );
+            CREATE TABLE configs (
+                key     TEXT PRIMARY KEY,
+                value   TEXT NOT NULL
+            );
+            INSERT INTO configs (key, value) VALUES ('db_version', '2');
+
+            INSERT INTO logs (id, timestamp, directory, content, tags, metadata, level) VALUES
+                (1, '2025-01-01T09:00:00+08:00', '/work', 'fix the parser', 'rust, dev,rust', 'oldhash1', 'info'),
+                (2, '2025-01-02T09:00:00+08:00', '/work', 'read the book', 'programming/rust', NULL, NULL),
+                (3, '2025-01-03T09:00:00+08:00', '/home', 'no tags here', '', 'oldhash3', 'warn'),
+                (4, '2025-01-01T09:00:00+08:00', '/work', 'fix the parser', NULL, 'oldhash4', 'info');
+            INSERT INTO backup (id, timestamp, directory, content, tags, metadata, level) VALUES
+                (1, '2024-12-31T09:00:00+08:00', '/work', 'removed', 'archived,dev', 'oldhash9', 'info');",
+        )
+        .unwrap();
+        drop(conn);
+
+        run_migrations(&path).unwrap();
+        let conn = Connection::open(&path).unwrap();
+        assert_eq!(get_db_version(&conn).unwrap(), DLOG_DB_VERSION);
+        (dir, conn)
+    }
+
+    fn single<T: rusqlite::types::FromSql>(conn: &Connection, sql: &str) -> T {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    fn log_tags(conn: &Connection, log_id: i64) -> Vec<String> {
+        let mut stmt = conn
+            .prepare(
+                "SELECT t.name FROM log_tags lt JOIN tags t ON t.tag_id = lt.tag_id
+                 WHERE lt.log_id = ?1 ORDER BY t.name",
+            )
+            .unwrap();
+        let rows = stmt.query_map([log_id], |row| row.get(0)).unwrap();
+        rows.collect::<Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn migration_backs_up_the_database_first() {
+        let (dir, _conn) = migrated_v2_fixture();
+        let backups = fs::read_dir(dir.path())
+            .unwrap()
+            .filter(|entry| {
+                let name = entry.as_ref().unwrap().file_name();
+                name.to_string_lossy().starts_with("dlog_bak_")
+            })
+            .count();
+        assert_eq!(backups, 1);
+    }
+
+    #[test]
+    fn v3_migration_splits_tag_strings() {
+        let (_dir, conn) = migrated_v2_fixture();
+
+        assert_eq!(log_tags(&conn, 1), vec!["dev", "rust"]);
+        assert_eq!(log_tags(&conn, 2), vec!["programming/rust"]);
+        assert!(log_tags(&conn, 3).is_empty());
+        assert!(log_tags(&conn, 4).is_empty());
+
+        // 子标签挂到自动创建的父标签下
+        let parent: String = single(
+            &conn,
+            "SELECT p.name FROM tags c JOIN tags p ON p.tag_id = c.parent_id
+             WHERE c.name = 'programming/rust'",
+        );
+        assert_eq!(parent, "programming");
+
+        // backup 中的标签名被登记，快照本身保持不变
+        let archived: i64 = single(&conn, "SELECT COUNT(*) FROM tags WHERE name = 'archived'");
+        assert_eq!(archived, 1);
+        let snapshot: String = single(&conn, "SELECT tags FROM backup WHERE id = 1");
+        assert_eq!(snapshot, "archived,dev");
+
+        let has_tags_column: bool = single(
+            &conn,
+            "SELECT EXISTS (SELECT 1 FROM pragma_table_info('logs') WHERE name = 'tags')",
+        );
+        assert!(!has_tags_column);
+    }
+
+    #[test]
+    fn v4_migration_indexes_existing_logs() {
+        let (_dir, conn) = migrated_v2_fixture();
+        let hits: i64 = single(
+            &conn,
+            "SELECT COUNT(*) FROM logs_fts WHERE logs_fts MATCH 'parser'",
+        );
+        assert_eq!(hits, 2);
+
+        // 触发器让之后的修改同步到索引
+        conn.execute(
+            "UPDATE logs SET content = 'rewrote the lexer' WHERE id = 1",
+            (),
+        )
+        .unwrap();
+        conn.execute("DELETE FROM logs WHERE id = 4", ()).unwrap();
+        let hits: i64 = single(
+            &conn,
+            "SELECT COUNT(*) FROM logs_fts WHERE logs_fts MATCH 'parser OR lexer'",
+        );
+        assert_eq!(hits, 1);
+    }
+
+    #[test]
+    fn v5_migration_leaves_writer_offset_unknown() {
+        let (_dir, conn) = migrated_v2_fixture();
+        for table in ["logs", "backup"] {
+            let known: i64 = single(&conn, &format!("SELECT COUNT(utc_offset) FROM {}", table));
+            assert_eq!(known, 0, "{}", table);
+        }
+    }
+
+    #[test]
+    fn v6_migration_wraps_metadata_in_json() {
+        let (_dir, conn) = migrated_v2_fixture();
+        for table in ["logs", "backup"] {
+            let not_json: i64 = single(
+                &conn,
+                &format!(
+                    "SELECT COUNT(*) FROM {} WHERE json_valid(metadata) = 0
+                     OR json_type(metadata) != 'object'
+                     OR json_extract(metadata, '$.id') IS NULL",
+                    table
+                ),
+            );
+            assert_eq!(not_json, 0, "{}", table);
+        }
+    }
+
+    #[test]
+    fn v7_migration_rewrites_identifiers_and_keeps_aliases() {
+        let (_dir, conn) = migrated_v2_fixture();
+        let id_of = |table: &str, id: i64| -> String {
+            conn.query_row(
+                &format!(
+                    "SELECT json_extract(metadata, '$.id') FROM {} WHERE id = ?1",
+                    table
+                ),
+                [id],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+        let alias = |old: &str| -> String {
+            conn.query_row(
+                "SELECT hash FROM id_aliases WHERE alias = ?1",
+                [old],
+                |row| row.get(0),
+            )
+            .unwrap()
+        };
+
+        let first = ident::log_hash("2025-01-01T09:00:00+08:00", "/work", "fix the parser");
+        assert_eq!(id_of("logs", 1), first);
+        assert_eq!(alias("oldhash1"), first);
+        // 没有 metadata 的日志以十六进制的 id 作为旧标识符
+        let second = ident::log_hash("2025-01-02T09:00:00+08:00", "/work", "read the book");
+        assert_eq!(id_of("logs", 2), second);
+        assert_eq!(alias("2"), second);
+
+        let removed = ident::log_hash("2024-12-31T09:00:00+08:00", "/work", "removed");
+        assert_eq!(id_of("backup", 1), removed);
+        assert_eq!(alias("oldhash9"), removed);
+
+        // 与第 1 条完全相同的日志保留原标识符，不违反唯一索引
+        assert_eq!(id_of("logs", 4), "oldhash4");
+    }
+
+    #[test]
+    fn backup_trim_evicts_earliest_removed() {
+        let (_dir, path) = temp_db_path();
+        initialize_db(&path).unwrap();
+        let conn = Connection::open(&path).unwrap();
+
+        // 先移除的日志时间戳较新，后移除的较旧
+        for i in 0..=MAX_BACKUP_ENTRIES {
+            conn.execute(
+                "INSERT INTO backup (timestamp, directory, content) VALUES (?1, '/', ?2)",
+                [
+                    format!("2025-01-01T00:00:{:02}Z", 59 - i % 60),
+                    i.to_string(),
+                ],
+            )
+            .unwrap();
+        }
+
+        let count: i64 = single(&conn, "SELECT COUNT(*) FROM backup");
+        assert_eq!(count as usize, MAX_BACKUP_ENTRIES);
+        let oldest: String = single(&conn, "SELECT content FROM backup ORDER BY id LIMIT 1");
+        assert_eq!(oldest, "1");
+        let newest: String = single(&conn, "SELECT content FROM backup ORDER BY id DESC LIMIT 1");
+        assert_eq!(newest, MAX_BACKUP_ENTRIES.to_string());
+    }
+}
@@ -0,0 +1,148 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/git.rs
+
+//! 记录日志时所在的 git 仓库上下文。
+//!
+//! 仓库根目录、分支与 HEAD 提交直接读取 `.git/HEAD` 与 refs 文件得到，
+//! 不依赖 git 命令。工作区是否有未提交的修改需要调用 `git status`，在大仓库中较慢，
+//! 因此只在调用 [`GitContext::with_dirty`] 时检查 (配置项 `git.record_dirty`)，
+//! 系统中没有 git 时该项留空。
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+/// 写入日志 metadata 中 `git` 字段的仓库上下文。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GitContext {
+    /// 工作区根目录
+    pub root: String,
+    /// 当前分支，HEAD 处于分离状态时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    /// HEAD 指向的完整提交哈希，尚无提交的新仓库为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// 工作区是否有未提交的修改，无法判断时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dirty: Option<bool>,
+}
+
+impl GitContext {
+    /// 从 `start` 开始向上查找 git 工作区，不在工作区中时返回 None。
+    pub fn discover(start: &Path) -> Option<Self> {
+        let (root, git_dir) = start.ancestors().find_map(|dir| {
+            let git_dir = resolve_git_dir(&dir.join(".git"))?;
+            Some((dir.to_path_buf(), git_dir))
+        })?;
+
+        let head = fs::read_to_string(git_dir.join("HEAD")).ok()?;
+        let head = head.trim();
+        let (branch, commit) = match head.strip_prefix("ref: ") {
+            Some(reference) => (
+                Some(
+                    reference
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(reference)
+                        .to_string(),
+                ),
+                read_ref(&git_dir, reference),
+            ),
+            // 分离的 HEAD 直接保存提交哈希
+            None => (None, Some(head.to_string())),
+        };
+
+        Some(GitContext {
+            root: root.to_str()?.to_string(),
+            branch,
+            commit,
+            dirty: None,
+        })
+    }
+
+    /// 调用 `git status` 检查工作区是否有未提交的修改，填写 `dirty` 字段。
+    pub fn with_dirty(self) -> Self {
+        let dirty = is_dirty(Path::new(&self.root));
+        GitContext { dirty, ..self }
+    }
+
+    /// 提交哈希的前 7 位
+    pub fn short_commit(&self) -> Option<&str> {
+        self.commit.as_deref().map(|c| &c[..c.len().min(7)])
+    }
+}
+
+impl std::fmt::Display for GitContext {
+    /// 形如 `main@1a2b3c4 (dirty) /path/to/repo`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.branch.as_deref().unwrap_or("(detached)"))?;
+        if let Some(commit) = self.short_commit() {
+            write!(f, "@{}", commit)?;
+        }
+        if self.dirty == Some(true) {
+            write!(f, " (dirty)")?;
+        }
+        write!(f, " {}", self.root)
+    }
+}
+
+/// `.git` 可能是目录，也可能是 worktree / submodule 使用的 `gitdir: <path>` 文件。
+fn resolve_git_dir(dot_git: &Path) -> Option<PathBuf> {
+    if dot_git.is_dir() {
+        return Some(dot_git.to_path_buf());
+    }
+    let content = fs::read_to_string(dot_git).ok()?;
+    let target = PathBuf::from(content.trim().strip_prefix("gitdir: ")?);
+    let git_dir = if target.is_absolute() {
+        target
+    } else {
+        dot_git.parent()?.join(target)
+    };
+    git_dir.is_dir().then_some(git_dir)
+}
+
+/// 读取一个引用指向的提交：先查找独立的 ref 文件，再查找 `packed-refs`。
+fn read_ref(git_dir: &Path, reference: &str) -> Option<String> {
+    // worktree 的分支引用保存在主仓库中，由 `commondir` 文件指出其位置
+    let common_dir = fs::read_to_string(git_dir.join("commondir"))
+        .ok()
+        .map(|dir| git_dir.join(dir.trim()))
+        .unwrap_or_else(|| git_dir.to_path_buf());
+
+    for dir in [git_dir, common_dir.as_path()] {
+        if let Ok(commit) = fs::read_to_string(dir.join(reference)) {
+            return Some(commit.trim().to_string());
+        }
+    }
+
+    let packed = fs::read_to_string(common_dir.join("packed-refs")).ok()?;
+    packed.lines().find_map(|line| {
+        let (commit, name) = line.split_once(' ')?;
+        (name == reference).then(|| commit.to_string())
+    })
+}
+
+/// 调用 `git status` 判断工作区是否有未提交的修改 (不考虑未跟踪的文件)。
+fn is_dirty(root: &Path) -> Option<bool> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    output.status.success().then_some(!output.stdout.is_empty())
+}
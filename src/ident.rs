@@ -0,0 +1,142 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/ident.rs
+
+//! 日志的哈希标识符 (数据库版本 7 起)。
+//!
+//! 标识符是写入时 `timestamp`、`directory` 与 `content` 的 SHA-1 (40 位十六进制)，
+//! 与 Rust 版本和平台无关。显示时像 git 一样使用最短的唯一前缀，至少 7 位。
+
+use sha1_smol::Sha1;
+
+/// 显示标识符时的最小长度
+pub const MIN_ABBREV_LEN: usize = 7;
+
+/// 计算一条日志的标识符，各字段之间以 `\0` 分隔。
+pub fn log_hash(timestamp: &str, directory: &str, content: &str) -> String {
+    let mut hasher = Sha1::new();
+    hasher.update(timestamp.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(directory.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(content.as_bytes());
+    hasher.digest().to_string()
+}
+
+/// 由 `seed` 生成一个 `len` 位的标记 (小写字母与数字)，`attempt` 不同时结果不同，
+/// 用于在标记冲突时重新生成。
+pub fn short_mark(seed: &str, attempt: u32, len: usize) -> String {
+    const ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyz";
+    let mut hasher = Sha1::new();
+    hasher.update(seed.as_bytes());
+    hasher.update(b"\0");
+    hasher.update(attempt.to_string().as_bytes());
+    hasher
+        .digest()
+        .bytes()
+        .iter()
+        .take(len)
+        .map(|b| ALPHABET[*b as usize % ALPHABET.len()] as char)
+        .collect()
+}
+
+/// 一组已知的标识符，用于计算每个标识符最短的唯一前缀。
+#[derive(Debug, Clone, Default)]
+pub struct Abbreviations {
+    sorted: Vec<String>,
+}
+
+impl Abbreviations {
+    pub fn new(mut ids: Vec<String>) -> Self {
+        ids.sort();
+        ids.dedup();
+        Abbreviations { sorted: ids }
+    }
+
+    /// `id` 的最短唯一前缀，至少 [`MIN_ABBREV_LEN`] 位。
+    pub fn abbreviate<'a>(&self, id: &'a str) -> &'a str {
+        // 排序后只有相邻的标识符可能拥有最长的公共前缀
+        let pos = self.sorted.partition_point(|other| other.as_str() < id);
+        let neighbours = [
+            pos.checked_sub(1).and_then(|i| self.sorted.get(i)),
+            self.sorted.get(pos).filter(|other| other.as_str() != id),
+            self.sorted.get(pos + 1),
+        ];
+        let shared = neighbours
+            .into_iter()
+            .flatten()
+            .map(|other| common_prefix_len(id, other))
+            .max()
+            .unwrap_or(0);
+
+        let len = (shared + 1).max(MIN_ABBREV_LEN).min(id.len());
+        &id[..len]
+    }
+}
+
+fn common_prefix_len(a: &str, b: &str) -> usize {
+    a.bytes().zip(b.bytes()).take_while(|(x, y)| x == y).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_hash_is_sha1_of_nul_separated_fields() {
+        let hash = log_hash("2025-01-01T00:00:00+00:00", "/tmp", "hello");
+        assert_eq!(hash.len(), 40);
+        assert_eq!(
+            hash,
+            Sha1::from("2025-01-01T00:00:00+00:00\0/tmp\0hello")
+                .digest()
+                .to_string()
+        );
+        // 字段边界不同的输入不会得到同一个标识符
+        assert_ne!(hash, log_hash("2025-01-01T00:00:00+00:00", "/tm", "phello"));
+    }
+
+    #[test]
+    fn abbreviations_use_shortest_unique_prefix() {
+        let ids = vec![
+            "abcdef0123".to_string(),
+            "abcdef0456".to_string(),
+            "abcdef0457".to_string(),
+            "ffff000000".to_string(),
+        ];
+        let abbrev = Abbreviations::new(ids);
+
+        // 唯一的标识符也至少显示 MIN_ABBREV_LEN 位
+        assert_eq!(abbrev.abbreviate("ffff000000"), "ffff000");
+        // 与相邻标识符的公共前缀再多一位
+        assert_eq!(abbrev.abbreviate("abcdef0123"), "abcdef01");
+        assert_eq!(abbrev.abbreviate("abcdef0456"), "abcdef0456");
+        assert_eq!(abbrev.abbreviate("abcdef0457"), "abcdef0457");
+    }
+
+    #[test]
+    fn abbreviations_handle_unknown_and_duplicate_ids() {
+        let abbrev = Abbreviations::new(vec![
+            "1234567aaa".to_string(),
+            "1234567aaa".to_string(),
+            "1234567bbb".to_string(),
+        ]);
+        // 重复的标识符不会让自身的前缀变长
+        assert_eq!(abbrev.abbreviate("1234567bbb"), "1234567b");
+        // 不在集合中的标识符同样与相邻的标识符区分
+        assert_eq!(abbrev.abbreviate("1234567abc"), "1234567ab");
+        // 标识符比最小长度还短时原样返回
+        assert_eq!(Abbreviations::default().abbreviate("abc"), "abc");
+    }
+}
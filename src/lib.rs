@@ -0,0 +1,53 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/lib.rs
+
+//! dlog 的核心库。
+//!
+//! 命令行程序 `dlog` 只是这个库的一层薄封装，其他工具 (构建脚本、编辑器插件等)
+//! 可以直接通过 [`Store`] 读写同一个数据库：
+//!
+//! ```no_run
+//! use dlog::{NewLog, Store};
+//!
+//! let mut store = Store::open("/path/to/dlog.db")?;
+//! store.migrate()?;
+//! let log = store.insert(&NewLog {
+//!     directory: "global".to_string(),
+//!     content: "nightly build passed".to_string(),
+//!     tags: vec!["ci".to_string()],
+//!     ..Default::default()
+//! })?;
+//! println!("{}", log.get_hash());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+pub mod config;
+pub mod db;
+pub mod git;
+pub mod ident;
+pub mod location;
+pub mod model;
+pub mod query;
+pub mod store;
+pub mod tags;
+pub mod timeparse;
+
+#[cfg(test)]
+mod testutil;
+
+pub use model::{Level, LevelFilter, Log, Session, Unit};
+pub use query::{GroupBy, Query, Scope};
+pub use store::{LogUpdate, NewLog, Store};
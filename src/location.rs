@@ -0,0 +1,126 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/location.rs
+
+//! 决定使用哪一个数据库文件。
+//!
+//! 按以下优先级查找：
+//! 1. `--db PATH`：直接使用给定的文件
+//! 2. `--journal NAME`：使用数据目录下的 `journals/NAME.db`
+//! 3. 环境变量 `DLOG_DB`
+//! 4. 项目日志：从当前目录向上查找的第一个 `.dlog/dlog.db`
+//! 5. 默认数据库 `<数据目录>/dlog.db`
+//!
+//! 数据目录为 `$XDG_DATA_HOME/dlog` (默认 `~/.local/share/dlog`)。
+//! 为兼容旧版本，如果 `~/.config/dlog/dlog.db` 已经存在，则继续使用 `~/.config/dlog`。
+
+use anyhow::{bail, Context, Result};
+use std::path::{Path, PathBuf};
+
+/// 默认数据库的文件名
+const DB_FILE_NAME: &str = "dlog.db";
+
+/// 存放具名日志本的子目录
+const JOURNALS_DIR: &str = "journals";
+
+/// 项目根目录下存放项目日志的目录
+pub const PROJECT_DIR: &str = ".dlog";
+
+/// 进行中会话的标记文件名
+const SESSION_MARKER: &str = "active-session";
+
+/// 根据命令行参数与环境变量决定数据库文件的路径。
+pub fn resolve_db_path(db: Option<&Path>, journal: Option<&str>) -> Result<PathBuf> {
+    if let Some(path) = db {
+        return Ok(path.to_path_buf());
+    }
+    if let Some(name) = journal {
+        return journal_path(name);
+    }
+    if let Some(path) = std::env::var_os("DLOG_DB").filter(|p| !p.is_empty()) {
+        return Ok(PathBuf::from(path));
+    }
+    if let Some(root) = find_project_root(&std::env::current_dir()?) {
+        return Ok(project_db_path(&root));
+    }
+    Ok(data_dir()?.join(DB_FILE_NAME))
+}
+
+/// 项目根目录 `root` 下的项目日志数据库路径。
+pub fn project_db_path(root: &Path) -> PathBuf {
+    root.join(PROJECT_DIR).join(DB_FILE_NAME)
+}
+
+/// 从 `start` 开始逐级向上查找包含 `.dlog/dlog.db` 的项目根目录。
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| project_db_path(dir).exists())
+        .map(Path::to_path_buf)
+}
+
+/// 如果 `db_path` 是一个项目日志数据库，返回它所属的项目根目录。
+pub fn project_root_of(db_path: &Path) -> Option<PathBuf> {
+    let dir = db_path.parent()?;
+    if dir.file_name()? != PROJECT_DIR {
+        return None;
+    }
+    let root = dir.parent()?;
+    // 相对路径 (如 `--db .dlog/dlog.db`) 需要先展开，之后才能计算日志目录的相对位置
+    let root = if root.as_os_str().is_empty() {
+        std::env::current_dir().ok()?
+    } else {
+        root.to_path_buf()
+    };
+    Some(root.canonicalize().unwrap_or(root))
+}
+
+/// 具名日志本 `name` 对应的数据库路径。
+pub fn journal_path(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if !valid {
+        bail!("日志本名称 '{}' 无效，只能包含字母、数字、- 和 _", name);
+    }
+    Ok(data_dir()?
+        .join(JOURNALS_DIR)
+        .join(format!("{}.db", name)))
+}
+
+/// 进行中会话的标记文件，内容为该会话所在数据库的绝对路径。
+///
+/// shell 钩子只在这个文件存在时才调用 dlog，并将命令记录到其中的数据库。
+pub fn session_marker_path() -> Result<PathBuf> {
+    Ok(data_dir()?.join(SESSION_MARKER))
+}
+
+/// dlog 存放数据库的目录。
+pub fn data_dir() -> Result<PathBuf> {
+    let home_dir = dirs::home_dir().context("无法确定用户主目录")?;
+
+    // 旧版本固定使用 ~/.config/dlog/dlog.db
+    let legacy_dir = home_dir.join(".config/dlog");
+    if legacy_dir.join(DB_FILE_NAME).exists() {
+        return Ok(legacy_dir);
+    }
+
+    // XDG 规范要求忽略相对路径
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| home_dir.join(".local/share"));
+    Ok(data_home.join("dlog"))
+}
@@ -16,65 +16,73 @@
 
 // 声明模块
 mod commands;
-mod db;
 
 // 引入依赖
-use anyhow::Result;
+use anyhow::{bail, Result};
 use clap::{Args, Parser, Subcommand};
+use dlog::model::Metadata;
+use dlog::{location, Level, LevelFilter, Store};
 use std::path::PathBuf;
 
-/// 初始化 dlog 环境，在 ~/.config/dlog/ 目录下创建数据库文件。
+/// 初始化 dlog 环境，创建数据库文件。
+///
+/// 与 --db 或 --journal 一同使用时，创建对应的数据库。
 #[derive(Args, Debug)]
 pub struct InitArgs {
     /// [保留] 用于未来的数据库结构升级
     #[arg(short, long)]
     pub upgrade: bool,
+
+    /// 在当前目录创建项目日志 (.dlog/dlog.db)，项目内的日志会以相对路径记录
+    #[arg(long)]
+    pub here: bool,
 }
 
 /// 记录一条新的日志。
 ///
 /// 如果不提供 -m/--message 参数，程序将进入交互式输入模式，
 /// 允许多行输入，直到按下 Ctrl+D 结束。
+/// 标准输入来自管道时 (如 `make 2>&1 | dlog log`)，直接读取管道中的全部内容。
 #[derive(Args, Debug)]
 pub struct LogArgs {
     /// 提供一条短消息直接记录 (类似 git commit -m)
     #[arg(short, long)]
     pub message: Option<String>,
 
-    /// 为此条日志附加一个或多个标签 (逗号分隔)
+    /// 使用 $VISUAL / $EDITOR 编辑日志内容 (可通过配置项 log.use_editor 设为默认)
+    #[arg(short, long, conflicts_with = "message")]
+    pub edit: bool,
+
+    /// 以文件的内容作为日志内容
+    #[arg(short, long, value_name = "PATH", conflicts_with_all = ["message", "edit"])]
+    pub file: Option<PathBuf>,
+
+    /// 只保留内容的最后 N 行，适合记录很长的构建或测试输出
+    #[arg(long, value_name = "N")]
+    pub tail: Option<usize>,
+
+    /// 为此条日志附加一个或多个标签 (逗号分隔，使用 / 表示层级，如 programming/rust)
     #[arg(short, long, value_name = "TAGS")]
     pub tags: Option<String>,
 
     /// 将此条日志记为全局日志，不与任何特定目录关联
     #[arg(short = 'g', long)]
     pub global: bool,
+
+    /// 附加自定义的元数据字段，可以多次使用 (如 --meta ticket=OPS-42)
+    #[arg(long, value_name = "KEY=VALUE", value_parser = parse_meta_field)]
+    pub meta: Vec<(String, String)>,
+
+    /// 日志级别: info, warn, error, todo, done (默认取配置项 log.default_level，未配置时为 info)
+    #[arg(short, long, value_name = "LEVEL")]
+    pub level: Option<Level>,
 }
 
-/// 查询并显示已记录的日志。
+/// 日志的筛选条件，`dlog get` 与 `dlog stats` 共用。
 ///
-/// 提供了丰富的筛选条件，可以组合使用来精确定位你需要的日志。
-/// 还可以附加--add-tag, --fix-path, --delete等动作参数来对查询结果进行批量操作。
+/// 默认只匹配当前目录的日志。
 #[derive(Args, Debug)]
-pub struct GetArgs {
-
-    /* 决定 SELECT/UPDATE/DELETE 阶段的SQL参数 */
- 
-    /// [动作] 为所有查询命中的日志追加一个新标签
-    #[arg(long, group = "action", value_name = "TAG")]
-    pub add_tag: Option<String>,
-
-    /// [动作] 批量修改所有查询命中的日志的目录信息
-    #[arg(long, group = "action", requires = "force", value_name = "PATH")]
-    pub fix_path: Option<String>,
-
-    /// [动作] 将查询命中的日志移动到备份区
-    #[arg(long, requires = "force", group = "action")]
-    pub delete: bool,
-
-    /// [安全] 执行危险操作如 --delete 时一同使用，以确认删除操作
-    #[arg(long)]
-    pub force: bool,
-
+pub struct FilterArgs {
     /* 决定 WHERE 子句的参数 其一 目录字段筛选 */
     /// 递归查询，匹配当前目录及其所有子目录下的日志。
     #[arg(short, long)]
@@ -105,19 +113,106 @@ pub struct GetArgs {
     #[arg(short = 'M', long, value_name = "MINUTES")]
     pub minute: Option<u32>,
 
+    /// 筛选此时间之后的日志 (如 2025-10-01, "2025-10-01 14:30", 3d, 2w, yesterday, "last monday")
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+
+    /// 筛选此时间之前的日志，只给出日期时包含当天 (格式同 --since)
+    #[arg(long, value_name = "TIME")]
+    pub until: Option<String>,
+
 
     /* 决定 WHERE 子句的参数 其三 内容匹配筛选 */
     /// 筛选包含特定标签的日志
     #[arg(long, value_name = "TAG")]
     pub tag: Option<String>,
 
+    /// 与 --tag 一同使用，同时匹配其子标签 (例如 programming 匹配 programming/rust)
+    #[arg(long, requires = "tag")]
+    pub subtags: bool,
+
     /// 筛选内容中包含特定关键字的日志
     #[arg(long, value_name = "KEYWORD")]
     pub keyword: Option<String>,
 
-    /// 最终显示最新的 N 条日志 (默认为 1)
-    #[arg(short, long, default_value_t = 1)]
-    pub num: u32,
+    /// 全文检索日志内容，结果按相关度排序。
+    /// 支持短语 ("a b")、AND/OR/NOT 与前缀匹配 (rust*)
+    #[arg(short, long, value_name = "QUERY")]
+    pub search: Option<String>,
+
+    /* 决定 WHERE 子句的参数 其四 git 上下文筛选 */
+    /// 只显示在该 git 分支上记录的日志
+    #[arg(long, value_name = "BRANCH")]
+    pub branch: Option<String>,
+
+    /// 只显示 HEAD 为该提交时记录的日志 (可以只提供提交哈希的前缀)
+    #[arg(long, value_name = "COMMIT")]
+    pub commit: Option<String>,
+
+    /// 只显示带有该元数据字段的日志，可以多次使用。只给出 KEY 时只要求字段存在
+    #[arg(long, value_name = "KEY[=VALUE]")]
+    pub meta: Vec<String>,
+
+    /// 只显示该级别的日志；使用 warn+ 这样的写法时显示不低于该级别的日志 (info < warn < error)
+    #[arg(short, long, value_name = "LEVEL")]
+    pub level: Option<LevelFilter>,
+
+    /// 隐藏已完成 (done) 的待办事项
+    #[arg(long)]
+    pub hide_done: bool,
+
+    /// 只显示某次会话中记录的日志 (编号或名称)，省略时为进行中或最近的一次会话。
+    /// 会话跨越多个目录，因此不再限制当前目录
+    #[arg(long, value_name = "ID|NAME", num_args = 0..=1, default_missing_value = "")]
+    pub session: Option<String>,
+
+    /// 只显示某个时间单元中记录的日志 (标记或名称)，省略时为进行中或最近的一个。
+    /// 不再限制当前目录
+    #[arg(long, value_name = "MARK|NAME", num_args = 0..=1, default_missing_value = "")]
+    pub unit: Option<String>,
+}
+
+/// 查询并显示已记录的日志。
+///
+/// 提供了丰富的筛选条件，可以组合使用来精确定位你需要的日志。
+/// 还可以附加--add-tag, --fix-path, --delete等动作参数来对查询结果进行批量操作。
+#[derive(Args, Debug)]
+pub struct GetArgs {
+
+    /* 决定 SELECT/UPDATE/DELETE 阶段的SQL参数 */
+ 
+    /// [动作] 为所有查询命中的日志追加一个新标签
+    #[arg(long, group = "action", value_name = "TAG")]
+    pub add_tag: Option<String>,
+
+    /// [动作] 批量修改所有查询命中的日志的目录信息
+    #[arg(long, group = "action", requires = "force", value_name = "PATH")]
+    pub fix_path: Option<String>,
+
+    /// [动作] 将查询命中的日志移动到备份区
+    #[arg(long, requires = "force", group = "action")]
+    pub delete: bool,
+
+    /// [安全] 执行危险操作如 --delete 时一同使用，以确认删除操作
+    #[arg(long)]
+    pub force: bool,
+
+    /* 决定 WHERE 子句的参数，与 dlog stats 共用 */
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// 只输出命中的日志条数 (不受 -n 限制)；与 --group-by unit 一起使用时输出每个时间单元的条数
+    #[arg(long, conflicts_with = "action")]
+    pub count: bool,
+
+    /// 分组: unit 按时间单元分组显示日志，每组前显示其开始、结束时间与持续时间；
+    /// day/week/month/tag/directory 输出每组的日志条数 (周以周一的日期表示)
+    #[arg(long, value_name = "KEY", value_parser = ["unit", "day", "week", "month", "tag", "directory"])]
+    pub group_by: Option<String>,
+
+    /// 最终显示最新的 N 条日志 (默认为 1，使用 --session、--unit 或 --group-by 时默认显示全部)
+    #[arg(short, long)]
+    pub num: Option<u32>,
 
     /// 是否按照时间逆序排序
     #[arg(long, default_value_t = false)]
@@ -148,6 +243,23 @@ pub struct GetArgs {
     */
 }
 
+/// 统计日志：按目录、标签、星期与小时的条数，连续记录的天数，平均长度，以及日历热力图。
+///
+/// 筛选条件与 dlog get 相同，默认只统计当前目录，使用 --all 统计整个数据库。
+#[derive(Args, Debug)]
+pub struct StatsArgs {
+    #[command(flatten)]
+    pub filter: FilterArgs,
+
+    /// 日历热力图显示的周数 (1 到 520)
+    #[arg(long, value_name = "N", default_value_t = 26, value_parser = clap::value_parser!(u32).range(1..=520))]
+    pub weeks: u32,
+
+    /// 输出格式
+    #[arg(short, long, value_name = "FORMAT", default_value = "table", value_parser = ["table", "json"])]
+    pub format: String,
+}
+
 /// 精确修改某一条已存在的日志。
 ///
 /// 通过唯一的标识符 (短哈希) 定位到具体某一条日志，然后对其内容、标签或目录进行更新。
@@ -164,6 +276,10 @@ pub struct FixArgs {
     #[arg(short, long)]
     pub content: Option<String>,
 
+    /// 使用 $VISUAL / $EDITOR 编辑日志的现有内容
+    #[arg(short, long, conflicts_with = "content")]
+    pub edit: bool,
+
     /// 更新日志的目录信息
     #[arg(short, long)]
     pub directory: Option<String>,
@@ -177,20 +293,355 @@ pub struct PopArgs {
     /// [必需] 提供一个或多个要移除的日志的唯一标识符 (短哈希)
     #[arg(required = true, num_args = 1..)]
     pub identifiers: Vec<String>,
+
+    /// 跳过确认提示，直接移除
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+/// 从备份区恢复被移除的日志。
+///
+/// 默认恢复最近被移除的一条日志，也可以通过 -n 恢复最近的 N 条，或者提供短哈希精确恢复。
+#[derive(Args, Debug)]
+pub struct RecoverArgs {
+    /// 提供一个或多个要恢复的日志的唯一标识符 (短哈希)
+    #[arg(num_args = 0.., conflicts_with_all = ["num", "list"])]
+    pub identifiers: Vec<String>,
+
+    /// 恢复最近被移除的 N 条日志
+    #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+    pub num: u32,
+
+    /// 仅列出备份区中的日志，不执行恢复
+    #[arg(short, long)]
+    pub list: bool,
+}
+
+/// 管理标签：查看使用情况，重命名、合并、移动或删除标签。
+#[derive(Args, Debug)]
+pub struct TagArgs {
+    #[command(subcommand)]
+    pub command: TagCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommands {
+    /// 列出所有标签及其使用次数
+    List {
+        /// 以树状结构显示标签层级
+        #[arg(long)]
+        tree: bool,
+    },
+    /// 重命名一个标签，所有使用它的日志会随之更新
+    Rename {
+        /// 原标签名
+        old: String,
+        /// 新标签名
+        new: String,
+    },
+    /// 将标签 FROM 合并到标签 INTO 中，随后删除 FROM
+    Merge {
+        /// 被合并的标签
+        from: String,
+        /// 合并的目标标签
+        into: String,
+    },
+    /// 调整标签的父标签
+    Move {
+        /// 需要移动的标签
+        tag: String,
+        /// 新的父标签 (不存在时自动创建)
+        #[arg(short, long, required_unless_present = "root")]
+        parent: Option<String>,
+        /// 移动到顶层，不再有父标签
+        #[arg(long, conflicts_with = "parent")]
+        root: bool,
+    },
+    /// 删除一个标签，并从所有日志中移除它
+    Delete {
+        /// 需要删除的标签
+        name: String,
+        /// 跳过确认提示，直接删除
+        #[arg(short, long)]
+        yes: bool,
+    },
+}
+
+/// 管理待办事项。
+///
+/// 待办事项就是级别为 todo 的日志，完成后级别变为 done。
+#[derive(Args, Debug)]
+pub struct TodoArgs {
+    #[command(subcommand)]
+    pub command: TodoCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TodoCommands {
+    /// 添加一条待办事项
+    Add {
+        /// 待办事项的内容
+        message: String,
+
+        /// 附加标签 (逗号分隔)
+        #[arg(short, long, value_name = "TAGS")]
+        tags: Option<String>,
+
+        /// 截止日期: YYYY-MM-DD, today, tomorrow, friday, 3d, 2w
+        #[arg(short, long, value_name = "DATE")]
+        due: Option<String>,
+
+        /// 记为全局待办事项，不与任何特定目录关联
+        #[arg(short = 'g', long)]
+        global: bool,
+    },
+    /// 列出当前目录及其子目录下以及全局的未完成待办事项，按截止日期排序
+    List {
+        /// 列出所有目录下的待办事项
+        #[arg(short, long)]
+        all: bool,
+    },
+    /// 将待办事项标记为完成
+    Done {
+        /// 一个或多个待办事项的唯一标识符 (短哈希)
+        #[arg(required = true, num_args = 1..)]
+        identifiers: Vec<String>,
+    },
+}
+
+/// 运行一条命令并记录其结果。
+///
+/// 命令的输出照常显示在终端上，结束后记录一条日志，包含命令行、退出状态、耗时与输出的最后几行，
+/// 并自动添加标签 cmd (失败时还有 failed)。dlog 以命令的退出码退出。
+///
+/// 示例: dlog run -t test -- cargo test
+#[derive(Args, Debug)]
+pub struct RunArgs {
+    /// 附加标签 (逗号分隔)
+    #[arg(short, long, value_name = "TAGS")]
+    pub tags: Option<String>,
+
+    /// 日志级别，默认成功时为 log.default_level 配置项，失败时为 error
+    #[arg(short, long)]
+    pub level: Option<Level>,
+
+    /// 记录输出的最后 N 行
+    #[arg(long, value_name = "N", default_value_t = 20)]
+    pub tail: usize,
+
+    /// 记为全局日志，不与任何特定目录关联
+    #[arg(short = 'g', long)]
+    pub global: bool,
+
+    /// 要运行的命令及其参数
+    #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true, value_name = "COMMAND")]
+    pub command: Vec<String>,
+}
+
+/// 管理命令记录会话。
+///
+/// 在 shell 配置中加入 `eval "$(dlog shell-init bash)"` 后，会话进行期间在该 shell 中
+/// 执行的每条命令都会连同退出状态与耗时被记录下来，手动记录的日志也会归入会话。
+#[derive(Args, Debug)]
+pub struct SessionArgs {
+    #[command(subcommand)]
+    pub command: SessionCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SessionCommands {
+    /// 开始一次会话
+    Start {
+        /// 会话名称，可用于 dlog get --session NAME
+        name: Option<String>,
+    },
+    /// 结束进行中的会话
+    Stop,
+    /// 列出所有会话
+    List,
+    /// 由 shell 钩子调用，记录一条刚刚执行完的命令
+    #[command(hide = true)]
+    Record {
+        /// 命令的退出码
+        #[arg(long, allow_negative_numbers = true)]
+        exit_code: i32,
+
+        /// 命令的耗时 (毫秒)
+        #[arg(long)]
+        duration: Option<u64>,
+
+        /// 命令行
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+}
+
+/// 管理时间单元。
+///
+/// 时间单元进行期间记录的日志都会带有它的 3 位标记，
+/// 之后可以用 `dlog get --unit` 或 `dlog get --group-by unit` 回顾。
+#[derive(Args, Debug)]
+pub struct UnitArgs {
+    #[command(subcommand)]
+    pub command: UnitCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UnitCommands {
+    /// 开始一个时间单元
+    Start {
+        /// 时间单元的名称，可用于 dlog get --unit NAME
+        name: Option<String>,
+    },
+    /// 结束进行中的时间单元
+    Stop,
+    /// 列出所有时间单元
+    List,
+}
+
+/// 记录工作时间。
+///
+/// `clock in` 写入一条开始计时的日志，`clock out` 为它记录结束时间，
+/// 之后可以用 `dlog report` 按标签与目录汇总用时。
+#[derive(Args, Debug)]
+pub struct ClockArgs {
+    #[command(subcommand)]
+    pub command: ClockCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ClockCommands {
+    /// 开始为一项任务计时，正在计时的任务会先自动结束
+    In {
+        /// 任务的描述
+        task: String,
+
+        /// 附加标签 (逗号分隔)，报告按标签汇总用时
+        #[arg(short, long, value_name = "TAGS")]
+        tags: Option<String>,
+
+        /// 记为全局条目，不与任何特定目录关联
+        #[arg(short = 'g', long)]
+        global: bool,
+    },
+    /// 结束正在计时的任务
+    Out,
+    /// 查看正在计时的任务
+    Status,
+}
+
+/// 按标签与目录汇总 `dlog clock` 记录的用时，默认统计本周 (周一起)。
+///
+/// 跨越统计区间边界的计时只计算区间内的部分，仍在计时的任务计算到当前时刻。
+#[derive(Args, Debug)]
+pub struct ReportArgs {
+    /// 统计本周 (默认)
+    #[arg(long, conflicts_with_all = ["today", "since", "until"])]
+    pub week: bool,
+
+    /// 统计今天
+    #[arg(long, conflicts_with_all = ["since", "until"])]
+    pub today: bool,
+
+    /// 统计该时刻之后的用时，支持与 get --since 相同的写法
+    #[arg(long, value_name = "TIME")]
+    pub since: Option<String>,
+
+    /// 统计该时刻之前的用时，默认为当前时刻
+    #[arg(long, value_name = "TIME")]
+    pub until: Option<String>,
+
+    /// 输出格式
+    #[arg(short, long, value_name = "FORMAT", default_value = "table", value_parser = ["table", "json"])]
+    pub format: String,
+}
+
+/// 输出 shell 钩子脚本，用于在会话期间自动记录命令。
+///
+/// 在 shell 配置中加入:
+///   bash: eval "$(dlog shell-init bash)"
+///   zsh:  eval "$(dlog shell-init zsh)"
+///   fish: dlog shell-init fish | source
+#[derive(Args, Debug)]
+pub struct ShellInitArgs {
+    /// shell 的种类
+    #[arg(value_parser = ["bash", "zsh", "fish"])]
+    pub shell: String,
+}
+
+/// 查看或修改当前数据库的配置项。
+#[derive(Args, Debug)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommands,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommands {
+    /// 列出所有配置项及其当前值
+    List,
+    /// 查看一个配置项的值
+    Get {
+        /// 配置项名称，如 log.default_level
+        key: String,
+    },
+    /// 设置一个配置项
+    Set {
+        /// 配置项名称，如 log.default_level
+        key: String,
+        /// 新的值
+        value: String,
+    },
+    /// 删除一个配置项，恢复默认行为
+    Unset {
+        /// 配置项名称
+        key: String,
+    },
+}
+
+/// 解析 `--meta key=value` 参数
+fn parse_meta_field(input: &str) -> Result<(String, String), String> {
+    let (key, value) = input
+        .split_once('=')
+        .ok_or_else(|| format!("'{}' 的格式应为 KEY=VALUE", input))?;
+    let key = key.trim();
+    if !Metadata::is_valid_key(key) {
+        return Err(format!("字段名 '{}' 无效，只能包含字母、数字、_ 和 -", key));
+    }
+    Ok((key.to_string(), value.to_string()))
 }
 
 #[derive(Subcommand)]
 enum Commands {
     Init(InitArgs),
     Log(LogArgs),
-    Get(GetArgs),
+    Get(Box<GetArgs>),
     Fix(FixArgs),
     Pop(PopArgs),
+    Recover(RecoverArgs),
+    Tag(TagArgs),
+    Todo(TodoArgs),
+    Run(RunArgs),
+    Session(SessionArgs),
+    Unit(UnitArgs),
+    Clock(ClockArgs),
+    Report(ReportArgs),
+    Stats(StatsArgs),
+    ShellInit(ShellInitArgs),
+    Config(ConfigArgs),
 }
 
 #[derive(Parser)]
 #[command(author="ABdeeglr Ramsay", version="0.1.1", about="一个为开发者设计的、轻量级的命令行日志工具。", long_about = None)]
 struct Cli {
+    /// 使用指定的数据库文件 (优先于 DLOG_DB 环境变量)
+    #[arg(long, global = true, value_name = "PATH", conflicts_with = "journal")]
+    db: Option<PathBuf>,
+
+    /// 使用具名日志本，数据库位于数据目录下的 journals/NAME.db
+    #[arg(long, global = true, value_name = "NAME")]
+    journal: Option<String>,
+
     #[command(subcommand)]
     command: Commands,
 }
@@ -200,39 +651,73 @@ struct Cli {
  *
  * 进行参数解析和任务分发
  */
-
 fn main() -> Result<()> {
     let cli = Cli::parse();
 
-    let db_path = {
-        let home_dir = dirs::home_dir().expect("Could not find home directory");
-        let mut path = PathBuf::from(&home_dir);
-        path.push(".config/dlog/dlog.db");
-        path
-    };
+    // 输出 shell 钩子不需要数据库
+    if let Commands::ShellInit(args) = &cli.command {
+        return commands::shell_init::handle_shell_init(args);
+    }
+
+    let db_path = location::resolve_db_path(cli.db.as_deref(), cli.journal.as_deref())?;
 
     // 如果用户在数据库尚未初始化的情况下执行子命令，则给出初始化提示
     if !db_path.exists() && !matches!(&cli.command, Commands::Init(_)) {
-        eprintln!("Error: database instance not initialized. Run `dlog init` firstly.");
+        let init_command = match (&cli.db, &cli.journal) {
+            (Some(path), _) => format!("dlog --db {} init", path.display()),
+            (_, Some(name)) => format!("dlog --journal {} init", name),
+            _ => "dlog init".to_string(),
+        };
+        eprintln!(
+            "Error: database instance not initialized at {}. Run `{}` firstly.",
+            db_path.display(),
+            init_command
+        );
         return Ok(());
     }
 
-    // 在运行时检查版本
-    if db_path.exists() {
-        db::run_migrations(&db_path)?;
+    // 初始化是唯一不需要打开已有数据库的子命令
+    if let Commands::Init(args) = &cli.command {
+        if args.here {
+            if cli.db.is_some() || cli.journal.is_some() {
+                bail!("--here 不能与 --db 或 --journal 一同使用");
+            }
+            let project_db = location::project_db_path(&std::env::current_dir()?);
+            return commands::init::handle_init(args, &project_db);
+        }
+        return commands::init::handle_init(args, &db_path);
     }
 
+    // 在运行时检查版本
+    let mut store = Store::open(&db_path)?;
+    store.migrate()?;
 
     // 核心的模式匹配与分发逻辑
     match &cli.command {
-        Commands::Init(args) => commands::init::handle_init(args, &db_path)?,
-        Commands::Log(args) => commands::log::handle_log(args, &db_path)?,
+        Commands::Init(_) => unreachable!("init 已在上方处理"),
+        Commands::Log(args) => commands::log::handle_log(args, &mut store)?,
         Commands::Get(args) => {
             //println!("{:#?}", args);
-            commands::get::handle_get(args, &db_path)?
+            commands::get::handle_get(args, &mut store)?
         },
-        Commands::Fix(args) => commands::fix::handle_fix(args, &db_path)?,
-        Commands::Pop(args) => commands::pop::handle_pop(args, &db_path)?,
+        Commands::Fix(args) => commands::fix::handle_fix(args, &mut store)?,
+        Commands::Pop(args) => commands::pop::handle_pop(args, &mut store)?,
+        Commands::Recover(args) => commands::recover::handle_recover(args, &mut store)?,
+        Commands::Tag(args) => commands::tag::handle_tag(args, &mut store)?,
+        Commands::Todo(args) => commands::todo::handle_todo(args, &mut store)?,
+        Commands::Run(args) => {
+            let code = commands::run::handle_run(args, &mut store)?;
+            // 以被运行命令的退出码退出，先关闭数据库连接
+            drop(store);
+            std::process::exit(code);
+        }
+        Commands::Session(args) => commands::session::handle_session(args, &mut store)?,
+        Commands::Unit(args) => commands::unit::handle_unit(args, &mut store)?,
+        Commands::Clock(args) => commands::clock::handle_clock(args, &mut store)?,
+        Commands::Report(args) => commands::report::handle_report(args, &mut store)?,
+        Commands::Stats(args) => commands::stats::handle_stats(args, &mut store)?,
+        Commands::ShellInit(_) => unreachable!("shell-init 已在上方处理"),
+        Commands::Config(args) => commands::config::handle_config(args, &mut store)?,
     };
 
     Ok(())
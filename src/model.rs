@@ -0,0 +1,492 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/model.rs
+
+//! 日志的数据模型。
+
+use crate::git::GitContext;
+use crate::timeparse::UserTimeZone;
+use chrono::{DateTime, FixedOffset, ParseError, Utc};
+use rusqlite::{Result, Row};
+use serde::{Deserialize, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// `metadata` 字段的 JSON 结构 (数据库版本 6 起)。
+///
+/// ```json
+/// {
+///   "id": "1a2b3c4d5e6f7a8b",
+///   "git": { "root": "/home/me/project", "branch": "main", "commit": "…", "dirty": false },
+///   "meta": { "ticket": "OPS-42", "host": "build-01" }
+/// }
+/// ```
+///
+/// - `id`：日志的哈希标识符，总是存在
+/// - `git`：在 git 工作区中记录时的仓库上下文，见 [`GitContext`]
+/// - `meta`：用户通过 `dlog log --meta key=value` 添加的字段，值均为字符串
+/// - `due`：待办事项的截止日期 `YYYY-MM-DD`
+/// - `done_at`：待办事项的完成时间 (UTC，RFC 3339)
+/// - `session`：记录时正在进行的会话编号，见 [`Session`]
+/// - `unit`：记录时正在进行的时间单元标记，见 [`Unit`]
+/// - `clock`：`dlog clock in` 写入的计时条目，见 [`Clock`]
+///
+/// 新的上下文信息作为新的顶层字段加入，不需要修改数据库结构；
+/// 读取时会忽略不认识的字段。
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Metadata {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git: Option<GitContext>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub meta: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub done_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clock: Option<Clock>,
+}
+
+/// 计时条目：从日志的时间戳开始计时，`dlog clock out` 时写入结束时间。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Clock {
+    /// 结束时间 (UTC，RFC 3339)，仍在计时的条目为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out: Option<String>,
+}
+
+impl Metadata {
+    /// 解析 `metadata` 字段，旧版本中单纯的哈希字符串会被视为只有 `id` 的对象。
+    pub fn parse(raw: &str) -> Option<Self> {
+        if raw.starts_with('{') {
+            serde_json::from_str(raw).ok()
+        } else {
+            Some(Metadata {
+                id: raw.to_string(),
+                ..Default::default()
+            })
+        }
+    }
+
+    /// 检查用户字段的名字：只允许字母、数字、`_` 与 `-`。
+    pub fn is_valid_key(key: &str) -> bool {
+        !key.is_empty()
+            && key
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    }
+
+    /// 用户字段在 SQLite JSON 函数中的路径
+    pub(crate) fn field_path(key: &str) -> String {
+        format!("$.meta.\"{}\"", key)
+    }
+}
+
+/// 日志的级别，保存在 `level` 字段中。
+///
+/// `info`、`warn`、`error` 表示严重程度，可以按“不低于某个级别”筛选；
+/// `todo` 与 `done` 表示待办事项及其完成状态。没有级别的旧日志视为 `info`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Level {
+    Info,
+    Warn,
+    Error,
+    Todo,
+    Done,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [Level::Info, Level::Warn, Level::Error, Level::Todo, Level::Done];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warn => "warn",
+            Level::Error => "error",
+            Level::Todo => "todo",
+            Level::Done => "done",
+        }
+    }
+
+    /// 严重程度，只有 info/warn/error 拥有
+    pub fn severity(&self) -> Option<u8> {
+        match self {
+            Level::Info => Some(0),
+            Level::Warn => Some(1),
+            Level::Error => Some(2),
+            Level::Todo | Level::Done => None,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for Level {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_lowercase();
+        Level::ALL
+            .into_iter()
+            .find(|level| level.as_str() == s)
+            .ok_or_else(|| format!("未知的级别 '{}'，可选: info, warn, error, todo, done", s))
+    }
+}
+
+/// `dlog get --level` 的筛选条件。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelFilter {
+    /// 只匹配该级别
+    Exact(Level),
+    /// 匹配严重程度不低于该级别的日志，写作 `warn+`
+    AtLeast(Level),
+}
+
+impl LevelFilter {
+    /// 满足条件的全部级别
+    pub fn levels(&self) -> Vec<Level> {
+        match self {
+            LevelFilter::Exact(level) => vec![*level],
+            LevelFilter::AtLeast(min) => Level::ALL
+                .into_iter()
+                .filter(|level| level.severity() >= min.severity())
+                .collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for LevelFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().strip_suffix('+') {
+            Some(min) => {
+                let min: Level = min.parse()?;
+                if min.severity().is_none() {
+                    return Err(format!("'{}+' 无效，只有 info/warn/error 可以使用 + 筛选", min));
+                }
+                Ok(LevelFilter::AtLeast(min))
+            }
+            None => Ok(LevelFilter::Exact(s.parse()?)),
+        }
+    }
+}
+
+/// 一次会话，保存在 `sessions` 表中 (数据库版本 8 起)。
+///
+/// 会话进行期间，shell 钩子记录的每一条命令以及手动记录的日志都会在 metadata 中
+/// 带有会话编号，之后可以用 `dlog get --session` 按时间顺序回顾。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Session {
+    pub id: i64,
+    pub name: Option<String>,
+    /// 开始时间 (UTC，RFC 3339)
+    pub started_at: String,
+    /// 结束时间，进行中的会话为空
+    pub ended_at: Option<String>,
+}
+
+impl Session {
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Session {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            started_at: row.get("started_at")?,
+            ended_at: row.get("ended_at")?,
+        })
+    }
+
+    /// 会话是否仍在进行
+    pub fn is_active(&self) -> bool {
+        self.ended_at.is_none()
+    }
+
+    /// 持续时间，进行中的会话计算到当前时刻
+    pub fn duration(&self) -> std::time::Duration {
+        span(&self.started_at, self.ended_at.as_deref())
+    }
+}
+
+impl fmt::Display for Session {
+    /// 形如 `#3 (refactor)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.id)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// 一个时间单元，保存在 `units` 表中 (数据库版本 9 起)。
+///
+/// 时间单元进行期间记录的日志在 metadata 中带有它的标记，
+/// 之后可以用 `dlog get --group-by unit` 按时间单元分组回顾。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Unit {
+    /// 3 位的标记，由小写字母与数字组成
+    pub mark: String,
+    pub name: Option<String>,
+    /// 开始时间 (UTC，RFC 3339)
+    pub started_at: String,
+    /// 结束时间，进行中的时间单元为空
+    pub ended_at: Option<String>,
+}
+
+impl Unit {
+    /// 标记的长度
+    pub const MARK_LEN: usize = 3;
+
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Unit {
+            mark: row.get("mark")?,
+            name: row.get("name")?,
+            started_at: row.get("started_at")?,
+            ended_at: row.get("ended_at")?,
+        })
+    }
+
+    /// 时间单元是否仍在进行
+    pub fn is_active(&self) -> bool {
+        self.ended_at.is_none()
+    }
+
+    /// 持续时间，进行中的时间单元计算到当前时刻
+    pub fn duration(&self) -> std::time::Duration {
+        span(&self.started_at, self.ended_at.as_deref())
+    }
+}
+
+/// 两个 RFC 3339 时刻之间的时长，没有结束时刻时计算到当前时刻
+fn span(start: &str, end: Option<&str>) -> std::time::Duration {
+    let parse = |time: &str| {
+        DateTime::parse_from_rfc3339(time)
+            .ok()
+            .map(|time| time.with_timezone(&Utc))
+    };
+    let start = parse(start).unwrap_or_else(Utc::now);
+    let end = end.and_then(parse).unwrap_or_else(Utc::now);
+    (end - start).to_std().unwrap_or_default()
+}
+
+impl fmt::Display for Unit {
+    /// 形如 `k3x (parser bug)`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.mark)?;
+        if let Some(name) = &self.name {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// 在 SQL 中取出日志的哈希标识符。
+///
+/// 数据库版本 6 起 `metadata` 总是带有 `id` 字段的 JSON 对象，
+/// 这里同时兼容尚未迁移的旧数据库中单纯的哈希字符串。
+pub(crate) const METADATA_ID_SQL: &str =
+    "CASE WHEN substr(metadata, 1, 1) = '{' THEN json_extract(metadata, '$.id') ELSE metadata END";
+
+/// 在 SQL 中取出 JSON 形式的 `metadata` 中的某个字段。
+///
+/// `path` 是一个 SQL 表达式，可以是字面量 `'$.git.branch'`，也可以是参数占位符 `?`。
+pub(crate) fn metadata_field_sql(path: &str) -> String {
+    format!(
+        "CASE WHEN substr(metadata, 1, 1) = '{{' THEN json_extract(metadata, {}) END",
+        path
+    )
+}
+
+/// 一条日志，`logs` 与 `backup` 两张表中的行都可以转换为它。
+#[derive(Debug, Clone, Serialize)]
+pub struct Log {
+    pub id: i32,
+    pub timestamp: String,
+    pub directory: String,
+    pub content: String,
+    pub tags: Option<String>,
+    /// JSON 形式的元数据，结构见 [`Metadata`]
+    #[serde(serialize_with = "serialize_metadata")]
+    pub metadata: Option<String>,
+    pub level: Option<String>,
+    /// 书写者当时相对 UTC 的偏移秒数，旧日志可能为空
+    pub utc_offset: Option<i32>,
+    /// 全文检索命中时的高亮片段，仅用于显示
+    #[serde(skip)]
+    pub snippet: Option<String>,
+}
+
+impl Log {
+    /// 从数据库行转换为 Log 结构体
+    pub fn from_row(row: &Row) -> Result<Self> {
+        Ok(Log {
+            id: row.get("id")?,
+            timestamp: row.get("timestamp")?,
+            directory: row.get("directory")?,
+            content: row.get("content")?,
+            tags: row.get("tags")?,
+            metadata: row.get("metadata")?,
+            level: row.get("level")?,
+            utc_offset: row.get("utc_offset")?,
+            snippet: None,
+        })
+    }
+
+    /// 将 UTC 时间转换为本地时间字符串
+    ///
+    /// "本地" 指阅读者的时区，即 `DLOG_TZ` 或系统时区。
+    pub fn get_local_time(&self) -> Option<String> {
+        let tz = UserTimeZone::from_env().unwrap_or(UserTimeZone::Local);
+        self.parse_timestamp().map(|utc_time| {
+            let local_time = tz.convert(utc_time.with_timezone(&Utc));
+            local_time.format("%Y-%m-%d %H:%M:%S").to_string()
+        })
+    }
+
+    /// 书写者当时的墙上时间，仅在与阅读者的时区不同时返回。
+    pub fn get_original_time(&self) -> Option<String> {
+        let offset = FixedOffset::east_opt(self.utc_offset?)?;
+        let utc_time = self.parse_timestamp()?.with_timezone(&Utc);
+
+        let tz = UserTimeZone::from_env().unwrap_or(UserTimeZone::Local);
+        if tz.convert(utc_time).offset() == &offset {
+            return None;
+        }
+
+        Some(
+            utc_time
+                .with_timezone(&offset)
+                .format("%Y-%m-%d %H:%M:%S %:z")
+                .to_string(),
+        )
+    }
+
+    /// 显示用的时间：阅读者的本地时间，时区不同时附带书写者的原始时间
+    pub fn get_display_time(&self) -> String {
+        let local_time = self.get_local_time().unwrap_or_else(|| self.timestamp.clone());
+        match self.get_original_time() {
+            Some(original) => format!("{} (书写于 {})", local_time, original),
+            None => local_time,
+        }
+    }
+
+    /// 解析存储的时间戳
+    pub fn parse_timestamp(&self) -> Option<DateTime<FixedOffset>> {
+        parse_timestamp(&self.timestamp)
+    }
+
+    /// 完整的哈希标识符，显示时请使用 [`crate::ident::Abbreviations`] 缩短
+    pub fn get_hash(&self) -> String {
+        self.get_metadata()
+            .map(|metadata| metadata.id)
+            .unwrap_or_default()
+    }
+
+    /// 解析后的元数据
+    pub fn get_metadata(&self) -> Option<Metadata> {
+        Metadata::parse(self.metadata.as_deref()?)
+    }
+
+    /// 日志的级别，没有级别的旧日志视为 info
+    pub fn get_level(&self) -> Level {
+        self.level
+            .as_deref()
+            .and_then(|level| level.parse().ok())
+            .unwrap_or(Level::Info)
+    }
+
+    /// 记录日志时所在的 git 仓库上下文
+    pub fn git(&self) -> Option<GitContext> {
+        self.get_metadata()?.git
+    }
+
+    /// 计时条目的开始与结束时间，仍在计时的条目没有结束时间；不是计时条目时返回 None
+    pub fn clock_span(&self) -> Option<(DateTime<Utc>, Option<DateTime<Utc>>)> {
+        let clock = self.get_metadata()?.clock?;
+        let start = self.parse_timestamp()?.with_timezone(&Utc);
+        let end = clock
+            .out
+            .and_then(|out| DateTime::parse_from_rfc3339(&out).ok())
+            .map(|out| out.with_timezone(&Utc));
+        Some((start, end))
+    }
+}
+
+/// JSON 输出中直接嵌入 metadata 对象，而不是一个转义后的字符串
+fn serialize_metadata<S: Serializer>(metadata: &Option<String>, s: S) -> Result<S::Ok, S::Error> {
+    metadata
+        .as_deref()
+        .and_then(Metadata::parse)
+        .serialize(s)
+}
+
+// 为 Log 实现 Display trait，用于简洁模式
+impl fmt::Display for Log {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let local_time = self.get_local_time().unwrap_or_else(|| self.timestamp.clone());
+        /*
+        let content_preview = if self.content.len() > 30 {
+            format!("{}...", &self.content[..27])
+        } else {
+            self.content.clone()
+        };
+        */
+        let content_preview = self.snippet.clone().unwrap_or_else(|| self.content.clone());
+
+        write!(f, "{} | {}", local_time, content_preview)
+    }
+}
+
+/// 解析数据库中的时间戳，兼容旧版本写入的格式
+pub(crate) fn parse_timestamp(timestamp: &str) -> Option<DateTime<FixedOffset>> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .or_else(|_| DateTime::parse_from_str(timestamp, "%+"))
+        .or_else(|_| manual_time_parse(timestamp))
+        .ok()
+}
+
+fn manual_time_parse(time_str: &str) -> Result<DateTime<FixedOffset>, ParseError> {
+    // 移除纳秒部分后的精度，保留最多6位小数
+    let simplified = if let Some(dot_pos) = time_str.find('.') {
+        if let Some(z_pos) = time_str[dot_pos..].find(['Z', '+']) {
+            let decimal_part = &time_str[dot_pos+1..dot_pos+z_pos];
+            let limited_decimal = if decimal_part.len() > 6 {
+                &decimal_part[..6] // 限制为6位小数
+            } else {
+                decimal_part
+            };
+
+            format!("{}.{}{}", 
+                    &time_str[..dot_pos], 
+                    limited_decimal,
+                    &time_str[dot_pos+z_pos..])
+        } else {
+            time_str.to_string()
+        }
+    } else {
+        time_str.to_string()
+    };
+
+    DateTime::parse_from_rfc3339(&simplified)
+}
+
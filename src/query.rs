@@ -0,0 +1,440 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/query.rs
+
+//! 查询构造器，与 `dlog get` 的筛选参数一一对应。
+//!
+//! ```no_run
+//! use dlog::{Query, Store};
+//!
+//! let store = Store::open("/path/to/dlog.db")?;
+//! let query = Query::new()
+//!     .directory("/home/me/project")
+//!     .recursive(true)
+//!     .tag("deploy")
+//!     .limit(10);
+//! for log in store.find(&query)? {
+//!     println!("{}", log);
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::model::{self, LevelFilter, Metadata};
+use crate::tags;
+use crate::timeparse::{to_sql_datetime, UserTimeZone};
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
+use rusqlite::ToSql;
+
+/// 查询的目录范围。
+#[derive(Debug, Clone, Default)]
+pub enum Scope {
+    /// 不限制目录
+    #[default]
+    All,
+    /// 限制在某个目录
+    Directory {
+        path: String,
+        /// 同时匹配子目录
+        recursive: bool,
+        /// 同时匹配全局日志
+        include_global: bool,
+    },
+}
+
+/// 聚合查询的分组方式，见 [`crate::Store::count_by`]。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupBy {
+    /// 按日期
+    Day,
+    /// 按周，每周从周一开始
+    Week,
+    /// 按月
+    Month,
+    /// 按标签，一条日志计入它的每一个标签
+    Tag,
+    /// 按目录
+    Directory,
+}
+
+/// 描述一次日志查询的全部筛选条件。
+///
+/// 默认查询所有目录、不限条数，结果按时间从新到旧排列。
+#[derive(Debug, Clone, Default)]
+pub struct Query {
+    scope: Scope,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    tag: Option<String>,
+    include_subtags: bool,
+    keyword: Option<String>,
+    search: Option<String>,
+    branch: Option<String>,
+    commit: Option<String>,
+    meta: Vec<(String, Option<String>)>,
+    level: Option<LevelFilter>,
+    hide_done: bool,
+    session: Option<i64>,
+    unit: Option<String>,
+    clock: bool,
+    limit: Option<u32>,
+    reverse: bool,
+}
+
+/// 拼装 SQL 时使用的参数列表
+pub(crate) type SqlParams = Vec<Box<dyn ToSql>>;
+
+impl Query {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 只查询某个目录的日志
+    pub fn directory(mut self, path: impl Into<String>) -> Self {
+        self.scope = Scope::Directory {
+            path: path.into(),
+            recursive: false,
+            include_global: false,
+        };
+        self
+    }
+
+    /// 与 [`Query::directory`] 一同使用，同时匹配子目录
+    pub fn recursive(mut self, enabled: bool) -> Self {
+        if let Scope::Directory { recursive, .. } = &mut self.scope {
+            *recursive = enabled;
+        }
+        self
+    }
+
+    /// 与 [`Query::directory`] 一同使用，同时匹配全局日志
+    pub fn include_global(mut self, enabled: bool) -> Self {
+        if let Scope::Directory { include_global, .. } = &mut self.scope {
+            *include_global = enabled;
+        }
+        self
+    }
+
+    /// 只查询该时刻 (含) 之后的日志，多次调用时取最晚的一个
+    pub fn since(mut self, time: DateTime<Utc>) -> Self {
+        self.since = Some(self.since.map_or(time, |t| t.max(time)));
+        self
+    }
+
+    /// 只查询该时刻 (不含) 之前的日志，多次调用时取最早的一个
+    pub fn until(mut self, time: DateTime<Utc>) -> Self {
+        self.until = Some(self.until.map_or(time, |t| t.min(time)));
+        self
+    }
+
+    /// 只查询 `tz` 时区中某一天的日志
+    pub fn on_day(self, tz: &UserTimeZone, date: NaiveDate) -> Result<Self> {
+        let (start, end) = tz.day_bounds(date)?;
+        Ok(self.since(start).until(end))
+    }
+
+    /// 只查询最近一段时间内的日志，时间跨度超出可表示的范围时返回错误
+    pub fn within(self, duration: TimeDelta) -> Result<Self> {
+        let since = Utc::now()
+            .checked_sub_signed(duration)
+            .context("时间跨度超出可表示的范围")?;
+        Ok(self.since(since))
+    }
+
+    /// 只查询带有该标签的日志
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tag = Some(tag.into().trim().trim_matches('/').to_string());
+        self
+    }
+
+    /// 与 [`Query::tag`] 一同使用，同时匹配其子孙标签
+    pub fn include_subtags(mut self, enabled: bool) -> Self {
+        self.include_subtags = enabled;
+        self
+    }
+
+    /// 只查询内容包含该关键字的日志
+    pub fn keyword(mut self, keyword: impl Into<String>) -> Self {
+        self.keyword = Some(keyword.into());
+        self
+    }
+
+    /// 使用 FTS5 全文检索，结果改为按相关度排序
+    pub fn search(mut self, query: impl Into<String>) -> Self {
+        self.search = Some(query.into());
+        self
+    }
+
+    /// 只查询在该 git 分支上记录的日志
+    pub fn branch(mut self, branch: impl Into<String>) -> Self {
+        self.branch = Some(branch.into());
+        self
+    }
+
+    /// 只查询 HEAD 为该提交时记录的日志，可以只给出提交哈希的前缀
+    pub fn commit(mut self, commit: impl Into<String>) -> Self {
+        self.commit = Some(commit.into().trim().to_lowercase());
+        self
+    }
+
+    /// 只查询带有该用户元数据字段的日志，`value` 为 None 时只要求字段存在。
+    /// 多次调用时需要同时满足。
+    pub fn meta(mut self, key: impl Into<String>, value: Option<String>) -> Self {
+        self.meta.push((key.into(), value));
+        self
+    }
+
+    /// 按级别筛选，没有级别的旧日志视为 info
+    pub fn level(mut self, filter: LevelFilter) -> Self {
+        self.level = Some(filter);
+        self
+    }
+
+    /// 隐藏已完成的待办事项
+    pub fn hide_done(mut self, enabled: bool) -> Self {
+        self.hide_done = enabled;
+        self
+    }
+
+    /// 只查询在某次会话中记录的日志
+    pub fn session(mut self, id: i64) -> Self {
+        self.session = Some(id);
+        self
+    }
+
+    /// 只查询在某个时间单元中记录的日志
+    pub fn unit(mut self, mark: impl Into<String>) -> Self {
+        self.unit = Some(mark.into());
+        self
+    }
+
+    /// 只查询 `dlog clock in` 写入的计时条目
+    pub fn clock(mut self, enabled: bool) -> Self {
+        self.clock = enabled;
+        self
+    }
+
+    /// 最多返回 N 条日志
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 反转排序 (时间从旧到新，或相关度从低到高)
+    pub fn reverse(mut self, enabled: bool) -> Self {
+        self.reverse = enabled;
+        self
+    }
+
+    /// 全文检索表达式 (如果有)
+    pub fn search_query(&self) -> Option<&str> {
+        self.search.as_deref()
+    }
+
+    /// 生成 `FROM ... WHERE ...` 部分以及对应的参数，可用于 SELECT 或聚合查询。
+    pub(crate) fn filter_sql(&self) -> (String, SqlParams) {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: SqlParams = Vec::new();
+
+        if let Scope::Directory {
+            path,
+            recursive,
+            include_global,
+        } = &self.scope
+        {
+            let matcher = if *recursive {
+                "(directory = ? OR directory LIKE ? ESCAPE '\\')"
+            } else {
+                "directory = ?"
+            };
+            if *include_global {
+                conditions.push(format!("({} OR directory == 'global')", matcher));
+            } else {
+                conditions.push(matcher.to_string());
+            }
+            params.push(Box::new(path.clone()));
+            if *recursive {
+                // 只匹配子目录：`/a` 不应匹配 `/ab`，目录名中的 `%` 与 `_` 按字面匹配
+                let parent = path.strip_suffix('/').unwrap_or(path);
+                params.push(Box::new(format!("{}/%", escape_like(parent))));
+            }
+        }
+
+        if let Some(since) = self.since {
+            conditions.push("datetime(timestamp) >= ?".to_string());
+            params.push(Box::new(to_sql_datetime(since)));
+        }
+        if let Some(until) = self.until {
+            conditions.push("datetime(timestamp) < ?".to_string());
+            params.push(Box::new(to_sql_datetime(until)));
+        }
+
+        if let Some(tag) = &self.tag {
+            conditions.push(tags::tag_filter_condition(self.include_subtags).to_string());
+            params.push(Box::new(tag.clone()));
+        }
+
+        if let Some(keyword) = &self.keyword {
+            conditions.push("content LIKE ?".to_string());
+            params.push(Box::new(format!("%{}%", keyword)));
+        }
+
+        if let Some(branch) = &self.branch {
+            conditions.push(format!("{} = ?", model::metadata_field_sql("'$.git.branch'")));
+            params.push(Box::new(branch.clone()));
+        }
+        if let Some(commit) = &self.commit {
+            conditions.push(format!("{} LIKE ?", model::metadata_field_sql("'$.git.commit'")));
+            params.push(Box::new(format!("{}%", commit)));
+        }
+
+        for (key, value) in &self.meta {
+            let field = model::metadata_field_sql("?");
+            params.push(Box::new(Metadata::field_path(key)));
+            match value {
+                Some(value) => {
+                    conditions.push(format!("{} = ?", field));
+                    params.push(Box::new(value.clone()));
+                }
+                None => conditions.push(format!("{} IS NOT NULL", field)),
+            }
+        }
+
+        if let Some(filter) = &self.level {
+            let levels = filter.levels();
+            conditions.push(format!(
+                "coalesce(level, 'info') IN ({})",
+                vec!["?"; levels.len()].join(",")
+            ));
+            for level in levels {
+                params.push(Box::new(level.as_str()));
+            }
+        }
+
+        if self.hide_done {
+            conditions.push("coalesce(level, 'info') != 'done'".to_string());
+        }
+
+        if let Some(session) = self.session {
+            conditions.push(format!("{} = ?", model::metadata_field_sql("'$.session'")));
+            params.push(Box::new(session));
+        }
+
+        if let Some(unit) = &self.unit {
+            conditions.push(format!("{} = ?", model::metadata_field_sql("'$.unit'")));
+            params.push(Box::new(unit.clone()));
+        }
+
+        if self.clock {
+            conditions.push(format!("{} IS NOT NULL", model::metadata_field_sql("'$.clock'")));
+        }
+
+        let where_clause = if !conditions.is_empty() {
+            format!(" WHERE {}", conditions.join(" AND "))
+        } else {
+            "".to_string()
+        };
+
+        // 全文检索：与 FTS5 的结果做连接，排序时使用 hits.fts_rank
+        if let Some(search) = &self.search {
+            params.insert(0, Box::new(search.clone()));
+            (
+                format!(
+                    "FROM logs
+                     JOIN (SELECT rowid AS fts_id, rank AS fts_rank FROM logs_fts WHERE logs_fts MATCH ?) hits
+                     ON hits.fts_id = logs.id{}",
+                    where_clause
+                ),
+                params,
+            )
+        } else {
+            (format!("FROM logs{}", where_clause), params)
+        }
+    }
+
+    /// 生成按排序与条数限制选出日志 id 的完整 SQL。
+    pub(crate) fn select_ids_sql(&self) -> (String, SqlParams) {
+        let (filter, mut params) = self.filter_sql();
+
+        let order_column = if self.search.is_some() {
+            "hits.fts_rank"
+        } else {
+            "timestamp"
+        };
+        // 时间默认从新到旧，相关度默认从高到低 (rank 越小越相关)
+        let descending = self.search.is_none() != self.reverse;
+
+        let sql = format!(
+            "SELECT id {} ORDER BY {} {} LIMIT ?",
+            filter,
+            order_column,
+            if descending { "DESC" } else { "ASC" }
+        );
+        // SQLite 中 LIMIT -1 表示不限制条数
+        params.push(Box::new(self.limit.map(i64::from).unwrap_or(-1)));
+
+        (sql, params)
+    }
+}
+
+/// 转义 LIKE 模式中的 `\`、`%` 与 `_`，配合 `ESCAPE '\'` 使用
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::temp_store;
+
+    #[test]
+    fn recursive_directory_matches_only_subdirectories() {
+        let (_dir, store) = temp_store(&[
+            ("2025-01-01T00:00:00Z", "/a", &[]),
+            ("2025-01-02T00:00:00Z", "/a/b", &[]),
+            ("2025-01-03T00:00:00Z", "/ab", &[]),
+            ("2025-01-04T00:00:00Z", "/abc/x", &[]),
+            ("2025-01-05T00:00:00Z", "/a_c/x", &[]),
+            ("2025-01-06T00:00:00Z", "/a%/y", &[]),
+            ("2025-01-07T00:00:00Z", "global", &[]),
+        ]);
+        let count = |path: &str, include_global: bool| {
+            let query = Query::new()
+                .directory(path)
+                .recursive(true)
+                .include_global(include_global);
+            store.count(&query).unwrap()
+        };
+
+        assert_eq!(count("/a", false), 2);
+        assert_eq!(count("/a", true), 3);
+        // `_` 与 `%` 按字面匹配
+        assert_eq!(count("/a_c", false), 1);
+        assert_eq!(count("/a%", false), 1);
+        assert_eq!(count("/", false), 6);
+        assert_eq!(store.count(&Query::new().directory("/a")).unwrap(), 1);
+    }
+
+    #[test]
+    fn escape_like_escapes_wildcards() {
+        assert_eq!(escape_like(r"50%_off\dir"), r"50\%\_off\\dir");
+    }
+}
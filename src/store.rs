@@ -0,0 +1,1202 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/store.rs
+
+//! `Store` 是 dlog 数据库的入口，命令行中的每个子命令都只是它的一层薄封装。
+
+use crate::config;
+use crate::db;
+use crate::location;
+use crate::git::GitContext;
+use crate::ident::{self, Abbreviations};
+use crate::model::{self, Clock, Level, Log, Metadata, Session, Unit};
+use crate::query::{GroupBy, Query, SqlParams};
+use crate::tags::{self, TagInfo};
+use crate::timeparse::UserTimeZone;
+use anyhow::{bail, Context, Result};
+use chrono::{Datelike, Days, Local, Months, NaiveDate, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// 一条待写入的新日志。
+#[derive(Debug, Clone, Default)]
+pub struct NewLog {
+    /// 日志所属的目录，全局日志为 `global`
+    pub directory: String,
+    pub content: String,
+    pub tags: Vec<String>,
+    /// 书写者相对 UTC 的偏移秒数，为 None 时使用系统本地时区
+    pub utc_offset: Option<i32>,
+    /// 书写时所在的 git 仓库上下文
+    pub git: Option<GitContext>,
+    /// 用户自定义的元数据字段
+    pub meta: BTreeMap<String, String>,
+    /// 日志级别，为 None 时使用配置项 `log.default_level`，未配置时为 info
+    pub level: Option<Level>,
+    /// 待办事项的截止日期
+    pub due: Option<NaiveDate>,
+    /// 所属的会话编号，见 [`Store::active_session`]
+    pub session: Option<i64>,
+    /// 所属的时间单元标记，见 [`Store::active_unit`]
+    pub unit: Option<String>,
+    /// 为 true 时写入一条开始计时的计时条目，见 [`Store::clock_out`]
+    pub clock: bool,
+}
+
+/// 对一条已有日志的修改，为 None 的字段保持不变。
+#[derive(Debug, Clone, Default)]
+pub struct LogUpdate {
+    pub content: Option<String>,
+    /// 覆盖日志原有的全部标签
+    pub tags: Option<Vec<String>>,
+    pub directory: Option<String>,
+}
+
+/// 一个 dlog 数据库。
+pub struct Store {
+    conn: Connection,
+    path: PathBuf,
+    /// 项目日志 (`<root>/.dlog/dlog.db`) 所属的项目根目录
+    project_root: Option<PathBuf>,
+}
+
+impl Store {
+    /// 打开一个已经存在的数据库。
+    ///
+    /// 不会自动执行版本迁移，需要时请调用 [`Store::migrate`]。
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            bail!("数据库不存在: {}", path.display());
+        }
+        let conn =
+            Connection::open(path).context(format!("无法连接到数据库: {}", path.display()))?;
+        // shell 钩子会在后台并发写入，遇到锁时等待而不是立即失败
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        Ok(Store {
+            conn,
+            path: path.to_path_buf(),
+            project_root: location::project_root_of(path),
+        })
+    }
+
+    /// 创建并初始化一个新的数据库，数据库已存在时直接打开它。
+    pub fn init(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            db::initialize_db(path)?;
+        }
+        Self::open(path)
+    }
+
+    /// 将数据库升级到当前程序所使用的版本，升级前会自动备份数据库文件。
+    pub fn migrate(&self) -> Result<()> {
+        db::run_migrations(&self.path)?;
+        Ok(())
+    }
+
+    /// 数据库文件的路径
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 项目日志所属的项目根目录，普通数据库返回 None
+    pub fn project_root(&self) -> Option<&Path> {
+        self.project_root.as_deref()
+    }
+
+    /// 将绝对路径转换为日志的 `directory` 字段。
+    ///
+    /// 项目日志中，项目内的目录以相对项目根目录的形式保存 (`.`、`./src` 等)，
+    /// 这样仓库被克隆到其他位置后日志仍然能对应上；项目外的目录保持绝对路径。
+    pub fn directory_key(&self, path: &Path) -> Result<String> {
+        // 项目根目录是规范化的路径，经由符号链接进入项目时 path 也需要先规范化才能比较
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+        let relative = self
+            .project_root
+            .as_deref()
+            .and_then(|root| canonical.strip_prefix(root).ok());
+        let key = match relative {
+            Some(rel) if rel.as_os_str().is_empty() => ".".to_string(),
+            Some(rel) => format!("./{}", rel.to_str().context("无法将目录转换为字符串")?),
+            None => path.to_str().context("无法将目录转换为字符串")?.to_string(),
+        };
+        Ok(key)
+    }
+
+    // ================================================================
+    // 写入
+    // ================================================================
+
+    /// 写入一条新日志，返回写入后的完整日志。
+    pub fn insert(&mut self, new_log: &NewLog) -> Result<Log> {
+        if new_log.content.trim().is_empty() {
+            bail!("日志内容不能为空");
+        }
+
+        let now = Utc::now();
+        let timestamp = now.to_rfc3339();
+        let utc_offset = new_log
+            .utc_offset
+            .unwrap_or_else(|| now.with_timezone(&Local).offset().local_minus_utc());
+
+        if let Some(key) = new_log.meta.keys().find(|k| !Metadata::is_valid_key(k)) {
+            bail!("元数据字段名 '{}' 无效，只能包含字母、数字、_ 和 -", key);
+        }
+        let metadata = serde_json::to_string(&Metadata {
+            id: ident::log_hash(&timestamp, &new_log.directory, &new_log.content),
+            git: new_log.git.clone(),
+            meta: new_log.meta.clone(),
+            due: new_log.due.map(|due| due.format("%Y-%m-%d").to_string()),
+            done_at: None,
+            session: new_log.session,
+            unit: new_log.unit.clone(),
+            clock: new_log.clock.then(Clock::default),
+        })?;
+
+        let level = match new_log.level {
+            Some(level) => level,
+            None => self
+                .config_get(config::DEFAULT_LEVEL)?
+                .and_then(|level| level.parse().ok())
+                .unwrap_or(Level::Info),
+        };
+
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "INSERT INTO logs (timestamp, directory, content, metadata, level, utc_offset) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![timestamp, new_log.directory, new_log.content, metadata, level.as_str(), utc_offset],
+        )?;
+        let log_id = tx.last_insert_rowid();
+        tags::set_log_tags(&tx, log_id, &new_log.tags)?;
+        tx.commit()?;
+
+        self.get(log_id as i32)
+    }
+
+    /// 在一个事务中修改一条日志，返回修改后的日志。
+    pub fn update(&mut self, id: i32, update: &LogUpdate) -> Result<Log> {
+        let tx = self.conn.transaction()?;
+        if let Some(content) = &update.content {
+            if content.trim().is_empty() {
+                bail!("日志内容不能为空");
+            }
+            tx.execute("UPDATE logs SET content = ?1 WHERE id = ?2", params![content, id])?;
+        }
+        if let Some(new_tags) = &update.tags {
+            tags::set_log_tags(&tx, id.into(), new_tags)?;
+        }
+        if let Some(directory) = &update.directory {
+            tx.execute(
+                "UPDATE logs SET directory = ?1 WHERE id = ?2",
+                params![directory, id],
+            )?;
+        }
+        tx.commit()?;
+
+        self.get(id)
+    }
+
+    /// 将一条待办事项标记为完成，并在 metadata 中记录完成时间。
+    pub fn mark_done(&mut self, id: i32) -> Result<Log> {
+        let log = self.get(id)?;
+        let hash = log.get_hash();
+        let short_hash = self.abbreviations()?.abbreviate(&hash).to_string();
+        match log.get_level() {
+            Level::Todo => {}
+            Level::Done => bail!("待办事项 {} 已经完成", short_hash),
+            _ => bail!("日志 {} 不是待办事项", short_hash),
+        }
+
+        self.conn.execute(
+            "UPDATE logs SET level = ?1, metadata = json_set(metadata, '$.done_at', ?2) WHERE id = ?3",
+            params![Level::Done.as_str(), Utc::now().to_rfc3339(), id],
+        )?;
+        self.get(id)
+    }
+
+    /// 仍在计时的计时条目
+    pub fn active_clock(&self) -> Result<Option<Log>> {
+        let sql = format!(
+            "SELECT id FROM logs WHERE {} IS NOT NULL AND {} IS NULL ORDER BY timestamp DESC LIMIT 1",
+            model::metadata_field_sql("'$.clock'"),
+            model::metadata_field_sql("'$.clock.out'")
+        );
+        let id: Option<i32> = self.conn.query_row(&sql, [], |row| row.get(0)).optional()?;
+        id.map(|id| self.get(id)).transpose()
+    }
+
+    /// 结束一条计时条目，记录结束时间。
+    pub fn clock_out(&mut self, id: i32) -> Result<Log> {
+        let log = self.get(id)?;
+        match log.clock_span() {
+            Some((_, None)) => {}
+            Some((_, Some(_))) => bail!("计时条目 {} 已经结束", log.content),
+            None => bail!("日志不是计时条目: {}", log.content),
+        }
+        self.conn.execute(
+            "UPDATE logs SET metadata = json_set(metadata, '$.clock.out', ?1) WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), id],
+        )?;
+        self.get(id)
+    }
+
+    /// 为一批日志追加一个标签，已包含该标签的日志保持不变。
+    ///
+    /// # Returns
+    /// 实际被修改的日志条数。
+    pub fn add_tag(&mut self, ids: &[i32], tag: &str) -> Result<usize> {
+        let tag = tag.trim();
+        if tag.is_empty() || tag.contains(',') {
+            bail!("标签不能为空，且一次只能追加一个标签");
+        }
+
+        let tx = self.conn.transaction()?;
+        let mut updated = 0;
+        for id in ids {
+            if tags::add_log_tag(&tx, (*id).into(), tag)? {
+                updated += 1;
+            }
+        }
+        tx.commit()?;
+
+        Ok(updated)
+    }
+
+    /// 将一批日志的目录修改为 `directory`。
+    ///
+    /// # Returns
+    /// 被修改的日志条数。
+    pub fn set_directory(&mut self, ids: &[i32], directory: &str) -> Result<usize> {
+        if ids.is_empty() {
+            return Ok(0);
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(",");
+        let mut params: Vec<Box<dyn ToSql>> = vec![Box::new(directory.to_string())];
+        for id in ids {
+            params.push(Box::new(*id));
+        }
+
+        let tx = self.conn.transaction()?;
+        let updated = tx.execute(
+            &format!("UPDATE logs SET directory = ? WHERE id IN ({})", placeholders),
+            params_from_iter(params),
+        )?;
+        tx.commit()?;
+
+        Ok(updated)
+    }
+
+    /// 将一批日志移动到备份区。
+    ///
+    /// # Returns
+    /// 被移动的日志条数。
+    pub fn move_to_backup(&mut self, ids: &[i32]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let moved = db::move_logs_to_backup(&tx, ids)?;
+        tx.commit()?;
+        Ok(moved)
+    }
+
+    /// 将备份区中的一批条目恢复到日志中。
+    ///
+    /// # Returns
+    /// 被恢复的日志条数。
+    pub fn restore(&mut self, backup_ids: &[i32]) -> Result<usize> {
+        let tx = self.conn.transaction()?;
+        let restored = db::restore_logs_from_backup(&tx, backup_ids)?;
+        tx.commit()?;
+        Ok(restored)
+    }
+
+    // ================================================================
+    // 读取
+    // ================================================================
+
+    /// 按查询条件选出日志 id，顺序即查询的排序。
+    pub fn find_ids(&self, query: &Query) -> Result<Vec<i32>> {
+        let (sql, params) = query.select_ids_sql();
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let ids = stmt
+            .query_map(params_from_iter(params), |row| row.get(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<i32>>>())
+            .map_err(|e| search_error(query, e))?;
+        Ok(ids)
+    }
+
+    /// 按查询条件读取完整的日志。
+    pub fn find(&self, query: &Query) -> Result<Vec<Log>> {
+        let ids = self.find_ids(query)?;
+        self.get_by_ids(&ids)
+    }
+
+    /// 满足查询条件的日志条数，忽略条数限制。
+    pub fn count(&self, query: &Query) -> Result<usize> {
+        let (filter, params) = query.filter_sql();
+        let count: i64 = self
+            .conn
+            .query_row(
+                &format!("SELECT COUNT(*) {}", filter),
+                params_from_iter(params),
+                |row| row.get(0),
+            )
+            .map_err(|e| search_error(query, e))?;
+        Ok(count as usize)
+    }
+
+    /// 按 `group` 分组统计满足查询条件的日志条数，忽略条数限制。
+    ///
+    /// 按时间分组时在 `tz` 中划分日期，结果从早到晚排列，中间没有日志的日、周、月计为 0，
+    /// 周以周一的日期、月以 `YYYY-MM` 表示；按标签或目录分组时按条数从多到少排列，
+    /// 没有标签的日志计入空字符串。
+    pub fn count_by(
+        &self,
+        query: &Query,
+        group: GroupBy,
+        tz: &UserTimeZone,
+    ) -> Result<Vec<(String, usize)>> {
+        let (filter, params) = query.filter_sql();
+        let sql = match group {
+            GroupBy::Directory => {
+                format!("SELECT directory, COUNT(*) {} GROUP BY directory", filter)
+            }
+            GroupBy::Tag => format!(
+                "SELECT coalesce(t.name, ''), COUNT(*)
+                 FROM (SELECT id {}) matched
+                 LEFT JOIN log_tags lt ON lt.log_id = matched.id
+                 LEFT JOIN tags t ON t.tag_id = lt.tag_id
+                 GROUP BY t.name",
+                filter
+            ),
+            GroupBy::Day | GroupBy::Week | GroupBy::Month => {
+                return self.count_by_time(query, &filter, params, group, tz);
+            }
+        };
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut counts = stmt
+            .query_map(params_from_iter(params), |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)? as usize))
+            })
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| search_error(query, e))?;
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Ok(counts)
+    }
+
+    /// 日期的划分取决于时区 (以及夏令时)，因此取出时间戳后在这里分组
+    fn count_by_time(
+        &self,
+        query: &Query,
+        filter: &str,
+        params: SqlParams,
+        group: GroupBy,
+        tz: &UserTimeZone,
+    ) -> Result<Vec<(String, usize)>> {
+        let bucket_of = |date: NaiveDate| match group {
+            GroupBy::Week => date - Days::new(date.weekday().num_days_from_monday().into()),
+            GroupBy::Month => date.with_day(1).unwrap_or(date),
+            _ => date,
+        };
+
+        let mut stmt = self.conn.prepare(&format!("SELECT timestamp {}", filter))?;
+        let timestamps = stmt
+            .query_map(params_from_iter(params), |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| search_error(query, e))?;
+        let mut buckets: BTreeMap<NaiveDate, usize> = BTreeMap::new();
+        for timestamp in timestamps {
+            if let Some(time) = model::parse_timestamp(&timestamp) {
+                let date = tz.convert(time.with_timezone(&Utc)).date_naive();
+                *buckets.entry(bucket_of(date)).or_default() += 1;
+            }
+        }
+
+        let (first, last) = match (buckets.keys().next(), buckets.keys().next_back()) {
+            (Some(&first), Some(&last)) => (first, last),
+            _ => return Ok(Vec::new()),
+        };
+        let mut counts = Vec::new();
+        let mut bucket = first;
+        while bucket <= last {
+            let key = match group {
+                GroupBy::Month => bucket.format("%Y-%m").to_string(),
+                _ => bucket.format("%Y-%m-%d").to_string(),
+            };
+            counts.push((key, buckets.get(&bucket).copied().unwrap_or(0)));
+            bucket = match group {
+                GroupBy::Week => bucket + Days::new(7),
+                GroupBy::Month => bucket + Months::new(1),
+                _ => bucket + Days::new(1),
+            };
+        }
+        Ok(counts)
+    }
+
+    /// 读取一条日志
+    pub fn get(&self, id: i32) -> Result<Log> {
+        self.get_by_ids(&[id])?
+            .pop()
+            .with_context(|| format!("日志 (id = {}) 不存在", id))
+    }
+
+    /// 按 id 读取完整的日志，结果与 `ids` 的顺序一致。
+    pub fn get_by_ids(&self, ids: &[i32]) -> Result<Vec<Log>> {
+        // 处理空 ID 列表的情况
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let placeholders = vec!["?"; ids.len()].join(",");
+        let sql = format!(
+            "SELECT id, timestamp, directory, content, {}, metadata, level, utc_offset
+             FROM logs
+             WHERE id IN ({})",
+            tags::LOGS_TAGS_COLUMN,
+            placeholders
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut logs = stmt
+            .query_map(params_from_iter(ids.iter()), Log::from_row)?
+            .collect::<rusqlite::Result<Vec<Log>>>()?;
+
+        // `IN (...)` 不保证返回顺序，这里按照给出的 ID 顺序重新排列
+        logs.sort_by_key(|log| ids.iter().position(|id| *id == log.id));
+
+        Ok(logs)
+    }
+
+    /// 按移除时间倒序读取备份区的条目，`limit` 为 None 时读取全部。
+    pub fn backup_entries(&self, limit: Option<u32>) -> Result<Vec<Log>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, timestamp, directory, content, tags, metadata, level, utc_offset
+             FROM backup
+             ORDER BY id DESC
+             LIMIT ?1",
+        )?;
+        // SQLite 中 LIMIT -1 表示不限制条数
+        let limit = limit.map(i64::from).unwrap_or(-1);
+        let logs = stmt
+            .query_map([limit], Log::from_row)?
+            .collect::<rusqlite::Result<Vec<Log>>>()?;
+        Ok(logs)
+    }
+
+    /// 为全文检索命中的日志生成高亮片段，命中词用 `open` 与 `close` 包裹。
+    pub fn snippets(
+        &self,
+        search: &str,
+        ids: &[i32],
+        open: &str,
+        close: &str,
+    ) -> Result<HashMap<i32, String>> {
+        let placeholders = vec!["?"; ids.len()].join(",");
+        let sql = format!(
+            "SELECT rowid, snippet(logs_fts, 0, ?, ?, '…', 16)
+             FROM logs_fts
+             WHERE logs_fts MATCH ? AND rowid IN ({})",
+            placeholders
+        );
+
+        let mut params: Vec<Box<dyn ToSql>> = vec![
+            Box::new(open.to_string()),
+            Box::new(close.to_string()),
+            Box::new(search.to_string()),
+        ];
+        for id in ids {
+            params.push(Box::new(*id));
+        }
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let rows =
+            stmt.query_map(params_from_iter(params), |row| Ok((row.get(0)?, row.get(1)?)))?;
+        Ok(rows.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // ================================================================
+    // 短哈希解析
+    // 像 git 一样，用户只需提供标识符的前缀即可定位一条日志。
+    // ================================================================
+
+    /// 根据短哈希前缀定位唯一的一条日志。
+    ///
+    /// 如果没有匹配或匹配到多条，返回带有候选列表的错误信息。
+    pub fn resolve(&self, identifier: &str) -> Result<i32> {
+        self.resolve_in("logs", identifier)
+    }
+
+    /// 根据短哈希前缀定位备份区中唯一的一条条目。
+    pub fn resolve_backup(&self, identifier: &str) -> Result<i32> {
+        self.resolve_in("backup", identifier)
+    }
+
+    /// 当前数据库中所有标识符 (包括备份区与旧标识符) 的最短唯一前缀。
+    pub fn abbreviations(&self) -> Result<Abbreviations> {
+        let sql = format!(
+            "SELECT {id} FROM logs
+             UNION ALL SELECT {id} FROM backup
+             UNION ALL SELECT alias FROM id_aliases",
+            id = model::METADATA_ID_SQL
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, Option<String>>(0))?
+            .filter_map(|id| id.transpose())
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(Abbreviations::new(ids))
+    }
+
+    fn resolve_in(&self, table: &str, identifier: &str) -> Result<i32> {
+        let prefix = identifier.trim().to_lowercase();
+        if prefix.is_empty() {
+            bail!("标识符不能为空");
+        }
+
+        // logs 表的标签来自关联表，backup 表保存的是字符串快照
+        let tags_column = if table == "logs" {
+            tags::LOGS_TAGS_COLUMN
+        } else {
+            "tags"
+        };
+        let sql = format!(
+            "SELECT id, timestamp, directory, content, {}, metadata, level, utc_offset
+             FROM {}
+             WHERE substr({id}, 1, length(?1)) = ?1
+                OR {id} IN (SELECT hash FROM id_aliases WHERE substr(alias, 1, length(?1)) = ?1)
+             ORDER BY timestamp DESC",
+            tags_column,
+            table,
+            id = model::METADATA_ID_SQL
+        );
+        let mut stmt = self.conn.prepare(&sql)?;
+        let candidates = stmt
+            .query_map([&prefix], Log::from_row)?
+            .collect::<rusqlite::Result<Vec<Log>>>()?;
+
+        match candidates.as_slice() {
+            [] => bail!("未找到标识符为 '{}' 的日志", identifier),
+            [log] => Ok(log.id),
+            _ => {
+                let abbrevs = self.abbreviations()?;
+                let listing: Vec<String> = candidates
+                    .iter()
+                    .map(|log| format!("  {}  {}", abbrevs.abbreviate(&log.get_hash()), log))
+                    .collect();
+                bail!(
+                    "标识符 '{}' 不唯一，匹配到 {} 条日志，请提供更长的前缀:\n{}",
+                    identifier,
+                    candidates.len(),
+                    listing.join("\n")
+                )
+            }
+        }
+    }
+
+    // ================================================================
+    // 会话
+    // ================================================================
+
+    /// 开始一次新的会话，已有会话在进行时返回错误。
+    pub fn start_session(&mut self, name: Option<&str>) -> Result<Session> {
+        if let Some(active) = self.active_session()? {
+            bail!("会话 {} 尚未结束，请先执行 `dlog session stop`", active);
+        }
+        let name = name.map(str::trim).filter(|name| !name.is_empty());
+        self.conn.execute(
+            "INSERT INTO sessions (name, started_at) VALUES (?1, ?2)",
+            params![name, Utc::now().to_rfc3339()],
+        )?;
+        self.session(self.conn.last_insert_rowid())
+    }
+
+    /// 结束进行中的会话，返回结束后的会话。
+    pub fn stop_session(&mut self) -> Result<Session> {
+        let active = self.active_session()?.context("当前没有进行中的会话")?;
+        self.conn.execute(
+            "UPDATE sessions SET ended_at = ?1 WHERE id = ?2",
+            params![Utc::now().to_rfc3339(), active.id],
+        )?;
+        self.session(active.id)
+    }
+
+    /// 进行中的会话
+    pub fn active_session(&self) -> Result<Option<Session>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT * FROM sessions WHERE ended_at IS NULL ORDER BY id DESC LIMIT 1",
+                [],
+                Session::from_row,
+            )
+            .optional()?)
+    }
+
+    /// 按编号读取一次会话
+    pub fn session(&self, id: i64) -> Result<Session> {
+        self.conn
+            .query_row("SELECT * FROM sessions WHERE id = ?1", [id], Session::from_row)
+            .optional()?
+            .with_context(|| format!("会话 #{} 不存在", id))
+    }
+
+    /// 所有会话，最近开始的排在前面
+    pub fn sessions(&self) -> Result<Vec<Session>> {
+        let mut stmt = self.conn.prepare("SELECT * FROM sessions ORDER BY id DESC")?;
+        let sessions = stmt
+            .query_map([], Session::from_row)?
+            .collect::<rusqlite::Result<Vec<Session>>>()?;
+        Ok(sessions)
+    }
+
+    /// 按编号 (可带 `#`) 或名称查找会话，同名时取最近的一次。
+    /// `key` 为空时返回进行中的会话，没有则返回最近的一次。
+    pub fn find_session(&self, key: &str) -> Result<Session> {
+        let key = key.trim();
+        if key.is_empty() {
+            return self.sessions()?.into_iter().next().context("还没有任何会话");
+        }
+        if let Ok(id) = key.trim_start_matches('#').parse::<i64>() {
+            return self.session(id);
+        }
+        self.conn
+            .query_row(
+                "SELECT * FROM sessions WHERE name = ?1 ORDER BY id DESC LIMIT 1",
+                [key],
+                Session::from_row,
+            )
+            .optional()?
+            .with_context(|| format!("未找到名为 '{}' 的会话", key))
+    }
+
+    // ================================================================
+    // 时间单元
+    // ================================================================
+
+    /// 开始一个新的时间单元，已有时间单元在进行时返回错误。
+    pub fn start_unit(&mut self, name: Option<&str>) -> Result<Unit> {
+        if let Some(active) = self.active_unit()? {
+            bail!("时间单元 {} 尚未结束，请先执行 `dlog unit stop`", active);
+        }
+        let name = name.map(str::trim).filter(|name| !name.is_empty());
+        let started_at = Utc::now().to_rfc3339();
+
+        // 标记只有 3 位，与已有的标记冲突时重新生成
+        let seed = format!("{}{}", started_at, name.unwrap_or_default());
+        for attempt in 0..1000 {
+            let mark = ident::short_mark(&seed, attempt, Unit::MARK_LEN);
+            let inserted = self.conn.execute(
+                "INSERT OR IGNORE INTO units (mark, name, started_at) VALUES (?1, ?2, ?3)",
+                params![mark, name, started_at],
+            )?;
+            if inserted > 0 {
+                return self.unit(&mark);
+            }
+        }
+        bail!("无法生成未被使用的时间单元标记")
+    }
+
+    /// 结束进行中的时间单元，返回结束后的时间单元。
+    pub fn stop_unit(&mut self) -> Result<Unit> {
+        let active = self.active_unit()?.context("当前没有进行中的时间单元")?;
+        self.conn.execute(
+            "UPDATE units SET ended_at = ?1 WHERE mark = ?2",
+            params![Utc::now().to_rfc3339(), active.mark],
+        )?;
+        self.unit(&active.mark)
+    }
+
+    /// 进行中的时间单元
+    pub fn active_unit(&self) -> Result<Option<Unit>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT * FROM units WHERE ended_at IS NULL ORDER BY started_at DESC LIMIT 1",
+                [],
+                Unit::from_row,
+            )
+            .optional()?)
+    }
+
+    /// 按标记读取一个时间单元
+    pub fn unit(&self, mark: &str) -> Result<Unit> {
+        self.conn
+            .query_row("SELECT * FROM units WHERE mark = ?1", [mark], Unit::from_row)
+            .optional()?
+            .with_context(|| format!("时间单元 '{}' 不存在", mark))
+    }
+
+    /// 所有时间单元，最近开始的排在前面
+    pub fn units(&self) -> Result<Vec<Unit>> {
+        let mut stmt = self.conn.prepare("SELECT * FROM units ORDER BY started_at DESC")?;
+        let units = stmt
+            .query_map([], Unit::from_row)?
+            .collect::<rusqlite::Result<Vec<Unit>>>()?;
+        Ok(units)
+    }
+
+    /// 按标记或名称查找时间单元，同名时取最近的一个。
+    /// `key` 为空时返回进行中的时间单元，没有则返回最近的一个。
+    pub fn find_unit(&self, key: &str) -> Result<Unit> {
+        let key = key.trim();
+        if key.is_empty() {
+            return self.units()?.into_iter().next().context("还没有任何时间单元");
+        }
+        self.conn
+            .query_row(
+                "SELECT * FROM units WHERE mark = lower(?1) OR name = ?1
+                 ORDER BY mark = lower(?1) DESC, started_at DESC LIMIT 1",
+                [key],
+                Unit::from_row,
+            )
+            .optional()?
+            .with_context(|| format!("未找到标记或名称为 '{}' 的时间单元", key))
+    }
+
+    // ================================================================
+    // 配置
+    // ================================================================
+
+    /// 读取一个配置项，未设置时返回 None
+    pub fn config_get(&self, key: &str) -> Result<Option<String>> {
+        config::find_key(key)?;
+        Ok(self
+            .conn
+            .query_row("SELECT value FROM configs WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?)
+    }
+
+    /// 设置一个配置项，返回规范化后保存的值
+    pub fn config_set(&mut self, key: &str, value: &str) -> Result<String> {
+        let value = (config::find_key(key)?.normalize)(value)?;
+        self.conn.execute(
+            "INSERT INTO configs (key, value) VALUES (?1, ?2)
+             ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+            params![key, value],
+        )?;
+        Ok(value)
+    }
+
+    /// 删除一个配置项，恢复默认行为
+    ///
+    /// # Returns
+    /// 配置项原本存在时返回 true。
+    pub fn config_unset(&mut self, key: &str) -> Result<bool> {
+        config::find_key(key)?;
+        Ok(self.conn.execute("DELETE FROM configs WHERE key = ?1", [key])? > 0)
+    }
+
+    // ================================================================
+    // 标签管理
+    // ================================================================
+
+    /// 列出所有标签及其使用次数
+    pub fn tags(&self) -> Result<Vec<TagInfo>> {
+        Ok(tags::list_tags(&self.conn)?)
+    }
+
+    /// 直接使用某个标签的日志条数
+    pub fn tag_usage(&self, name: &str) -> Result<i64> {
+        let tag_id = self.require_tag(name)?;
+        Ok(self.conn.query_row(
+            "SELECT COUNT(*) FROM log_tags WHERE tag_id = ?1",
+            [tag_id],
+            |row| row.get(0),
+        )?)
+    }
+
+    /// 重命名一个标签
+    pub fn rename_tag(&mut self, old: &str, new: &str) -> Result<()> {
+        let new = new.trim();
+        if new.is_empty() || new.contains(',') {
+            bail!("新标签名不能为空，且不能包含逗号");
+        }
+        let tag_id = self.require_tag(old)?;
+        if tags::find_tag(&self.conn, new)?.is_some() {
+            bail!(
+                "标签 '{}' 已存在，如需合并请使用 `dlog tag merge {} {}`",
+                new,
+                old,
+                new
+            );
+        }
+        let tx = self.conn.transaction()?;
+        tx.execute(
+            "UPDATE tags SET name = ?1 WHERE tag_id = ?2",
+            params![new, tag_id],
+        )?;
+        tags::rewrite_backup_tags(&tx, old.trim(), Some(new))?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 将标签 `from` 合并到 `into` 中。
+    ///
+    /// # Returns
+    /// 因此获得 `into` 标签的日志条数。
+    pub fn merge_tags(&mut self, from: &str, into: &str) -> Result<usize> {
+        let from_id = self.require_tag(from)?;
+        let into_id = self.require_tag(into)?;
+        if tags::is_descendant_of(&self.conn, into_id, from_id)? {
+            bail!("不能将标签 '{}' 合并到它自身或它的子标签 '{}' 中", from, into);
+        }
+        let tx = self.conn.transaction()?;
+        let moved = tags::merge_tag(&tx, from_id, into_id)?;
+        tags::rewrite_backup_tags(&tx, from.trim(), Some(into.trim()))?;
+        tx.commit()?;
+        Ok(moved)
+    }
+
+    /// 调整标签的父标签，`parent` 为 None 时移动到顶层。
+    pub fn move_tag(&mut self, tag: &str, parent: Option<&str>) -> Result<()> {
+        let tag_id = self.require_tag(tag)?;
+        let tx = self.conn.transaction()?;
+        let parent_id = match parent {
+            Some(parent) => {
+                let parent_id = tags::ensure_tag(&tx, parent.trim())?;
+                if tags::is_descendant_of(&tx, parent_id, tag_id)? {
+                    bail!("不能将标签 '{}' 移动到它自身或它的子标签 '{}' 之下", tag, parent);
+                }
+                Some(parent_id)
+            }
+            None => None,
+        };
+        tx.execute(
+            "UPDATE tags SET parent_id = ?1 WHERE tag_id = ?2",
+            params![parent_id, tag_id],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// 删除一个标签并从所有日志中移除它。
+    ///
+    /// # Returns
+    /// 受影响的日志条数。
+    pub fn delete_tag(&mut self, name: &str) -> Result<usize> {
+        let tag_id = self.require_tag(name)?;
+        let tx = self.conn.transaction()?;
+        let unlinked = tags::delete_tag(&tx, tag_id)?;
+        tags::rewrite_backup_tags(&tx, name.trim(), None)?;
+        tx.commit()?;
+        Ok(unlinked)
+    }
+
+    /// 查找标签，不存在时返回错误。
+    fn require_tag(&self, name: &str) -> Result<i64> {
+        tags::find_tag(&self.conn, name.trim())?.with_context(|| format!("标签 '{}' 不存在", name))
+    }
+}
+
+/// 全文检索表达式的语法错误在执行查询时才会出现，改为提示用户检查表达式
+fn search_error(query: &Query, e: rusqlite::Error) -> anyhow::Error {
+    match query.search_query() {
+        Some(search) => anyhow::anyhow!("全文检索表达式 '{}' 无效: {}", search, e),
+        None => e.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{east8, temp_store};
+    use chrono::{DateTime, FixedOffset};
+
+    #[test]
+    fn logs_round_trip_through_backup() {
+        let (_dir, mut store) = temp_store(&[]);
+        let log = store
+            .insert(&NewLog {
+                directory: "/work".to_string(),
+                content: "first draft".to_string(),
+                tags: vec!["rust".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        let hash = log.get_hash();
+        assert_eq!(
+            store.resolve(&hash[..ident::MIN_ABBREV_LEN]).unwrap(),
+            log.id
+        );
+
+        let updated = store
+            .update(
+                log.id,
+                &LogUpdate {
+                    content: Some("final draft".to_string()),
+                    tags: Some(vec!["dev".to_string()]),
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(updated.content, "final draft");
+        assert_eq!(updated.tags.as_deref(), Some("dev"));
+        // 修改内容不会改变标识符
+        assert_eq!(updated.get_hash(), hash);
+
+        assert_eq!(store.move_to_backup(&[log.id]).unwrap(), 1);
+        assert_eq!(store.count(&Query::new()).unwrap(), 0);
+        let backup = store.backup_entries(None).unwrap();
+        assert_eq!(backup.len(), 1);
+        assert_eq!(backup[0].tags.as_deref(), Some("dev"));
+
+        // 恢复后保留原来的时间戳、标签与标识符
+        assert_eq!(store.restore(&[backup[0].id]).unwrap(), 1);
+        let restored = store.find(&Query::new().directory("/work")).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored[0].timestamp, log.timestamp);
+        assert_eq!(restored[0].tags.as_deref(), Some("dev"));
+        assert_eq!(restored[0].get_hash(), hash);
+        assert!(store.backup_entries(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_search_expression_is_reported_the_same_everywhere() {
+        let (_dir, store) = temp_store(&[("2025-01-01T08:00:00+08:00", "/", &[])]);
+        let query = Query::new().search("\"unterminated");
+        let expected = "全文检索表达式 '\"unterminated' 无效";
+
+        let errors = [
+            store.find(&query).unwrap_err(),
+            store.count(&query).unwrap_err(),
+            store.count_by(&query, GroupBy::Tag, &east8()).unwrap_err(),
+            store.count_by(&query, GroupBy::Week, &east8()).unwrap_err(),
+        ];
+        for error in errors {
+            assert!(error.to_string().starts_with(expected), "{}", error);
+        }
+        assert_eq!(store.count(&Query::new().search("written")).unwrap(), 1);
+    }
+
+    fn tag_names(store: &Store) -> Vec<String> {
+        store
+            .tags()
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect()
+    }
+
+    #[test]
+    fn tag_changes_rewrite_backup_snapshots() {
+        let (_dir, mut store) = temp_store(&[
+            ("2025-01-01T08:00:00+08:00", "/", &["keep", "old"]),
+            ("2025-01-02T08:00:00+08:00", "/", &["dup", "gone"]),
+            ("2025-01-03T08:00:00+08:00", "/", &["old", "dup"]),
+        ]);
+        store.move_to_backup(&[1, 2]).unwrap();
+
+        store.rename_tag("old", "new").unwrap();
+        assert_eq!(store.merge_tags("dup", "keep").unwrap(), 1);
+        store.delete_tag("gone").unwrap();
+
+        let mut snapshots: Vec<String> = store
+            .backup_entries(None)
+            .unwrap()
+            .into_iter()
+            .filter_map(|log| log.tags)
+            .collect();
+        snapshots.sort();
+        assert_eq!(snapshots, vec!["keep", "keep,new"]);
+
+        // 恢复后不会重新出现已经改名、合并或删除的标签
+        let backup_ids: Vec<i32> = store
+            .backup_entries(None)
+            .unwrap()
+            .iter()
+            .map(|log| log.id)
+            .collect();
+        store.restore(&backup_ids).unwrap();
+        assert_eq!(tag_names(&store), vec!["keep", "new"]);
+        assert_eq!(store.tag_usage("keep").unwrap(), 3);
+    }
+
+    #[test]
+    fn moved_tags_are_matched_by_their_new_parent() {
+        let (_dir, mut store) = temp_store(&[
+            ("2025-01-01T08:00:00+08:00", "/", &["work"]),
+            ("2025-01-02T08:00:00+08:00", "/", &["misc/rust"]),
+        ]);
+        let subtree = |store: &Store, tag: &str| {
+            store
+                .count(&Query::new().tag(tag).include_subtags(true))
+                .unwrap()
+        };
+        assert_eq!(subtree(&store, "misc"), 1);
+
+        // 移动后标签名不变，层级以 parent_id 为准
+        store.move_tag("misc/rust", Some("work")).unwrap();
+        assert_eq!(subtree(&store, "work"), 2);
+        assert_eq!(subtree(&store, "misc"), 0);
+
+        assert!(store.move_tag("work", Some("misc/rust")).is_err());
+        assert!(store.merge_tags("work", "misc/rust").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn directory_key_resolves_symlinks_into_the_project() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let link = dir.path().join("link");
+        std::os::unix::fs::symlink(&root, &link).unwrap();
+
+        let store = Store::init(location::project_db_path(&root)).unwrap();
+        assert_eq!(store.directory_key(&link).unwrap(), ".");
+        assert_eq!(store.directory_key(&link.join("src")).unwrap(), "./src");
+
+        // 项目外的目录保持原样
+        let outside = dir.path().join("elsewhere");
+        assert_eq!(
+            store.directory_key(&outside).unwrap(),
+            outside.to_str().unwrap()
+        );
+    }
+
+    #[test]
+    fn meta_fields_are_stored_and_filterable() {
+        let (_dir, mut store) = temp_store(&[]);
+        for (content, ticket) in [("a", Some("OPS-1")), ("b", Some("OPS-2")), ("c", None)] {
+            store
+                .insert(&NewLog {
+                    directory: "/".to_string(),
+                    content: content.to_string(),
+                    meta: ticket
+                        .map(|t| BTreeMap::from([("ticket".to_string(), t.to_string())]))
+                        .unwrap_or_default(),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+
+        let with_ticket = Query::new().meta("ticket", None);
+        assert_eq!(store.count(&with_ticket).unwrap(), 2);
+        let ops1 = store
+            .find(&Query::new().meta("ticket", Some("OPS-1".to_string())))
+            .unwrap();
+        assert_eq!(ops1.len(), 1);
+        assert_eq!(ops1[0].content, "a");
+        let metadata = ops1[0].get_metadata().unwrap();
+        assert_eq!(
+            metadata.meta.get("ticket").map(String::as_str),
+            Some("OPS-1")
+        );
+        assert_eq!(metadata.id, ops1[0].get_hash());
+
+        let invalid = NewLog {
+            directory: "/".to_string(),
+            content: "d".to_string(),
+            meta: BTreeMap::from([("bad key".to_string(), "x".to_string())]),
+            ..Default::default()
+        };
+        assert!(store.insert(&invalid).is_err());
+    }
+
+    fn counts(pairs: &[(&str, usize)]) -> Vec<(String, usize)> {
+        pairs.iter().map(|(key, n)| (key.to_string(), *n)).collect()
+    }
+
+    #[test]
+    fn count_by_week_splits_on_monday_in_user_timezone() {
+        let (_dir, store) = temp_store(&[
+            // 2024-12-31 是周二，与 2025 年的头几天同属一周
+            ("2024-12-31T10:00:00+08:00", "/", &[]),
+            // 东八区的周日 23:30
+            ("2025-01-05T15:30:00+00:00", "/", &[]),
+            // UTC 仍是周日，东八区已是周一 00:30
+            ("2025-01-05T16:30:00+00:00", "/", &[]),
+            // 中间空出一周
+            ("2025-01-20T09:00:00+08:00", "/", &[]),
+        ]);
+
+        let weeks = store
+            .count_by(&Query::new(), GroupBy::Week, &east8())
+            .unwrap();
+        assert_eq!(
+            weeks,
+            counts(&[
+                ("2024-12-30", 2),
+                ("2025-01-06", 1),
+                ("2025-01-13", 0),
+                ("2025-01-20", 1),
+            ])
+        );
+
+        // 在 UTC 中划分时，三条日志都落在同一周
+        let utc = UserTimeZone::Fixed(FixedOffset::east_opt(0).unwrap());
+        let weeks = store.count_by(&Query::new(), GroupBy::Week, &utc).unwrap();
+        assert_eq!(weeks[0], ("2024-12-30".to_string(), 3));
+    }
+
+    #[test]
+    fn count_by_day_and_month_fill_gaps() {
+        let (_dir, store) = temp_store(&[
+            ("2025-01-31T23:30:00+08:00", "/", &[]),
+            ("2025-02-02T08:00:00+08:00", "/", &[]),
+            ("2025-04-01T08:00:00+08:00", "/", &[]),
+        ]);
+        let tz = east8();
+
+        let months = store.count_by(&Query::new(), GroupBy::Month, &tz).unwrap();
+        assert_eq!(
+            months,
+            counts(&[
+                ("2025-01", 1),
+                ("2025-02", 1),
+                ("2025-03", 0),
+                ("2025-04", 1)
+            ])
+        );
+
+        let query = Query::new().until(
+            DateTime::parse_from_rfc3339("2025-02-03T00:00:00+08:00")
+                .unwrap()
+                .with_timezone(&Utc),
+        );
+        let days = store.count_by(&query, GroupBy::Day, &tz).unwrap();
+        assert_eq!(
+            days,
+            counts(&[("2025-01-31", 1), ("2025-02-01", 0), ("2025-02-02", 1)])
+        );
+
+        let empty = Query::new().keyword("nothing matches");
+        assert!(store
+            .count_by(&empty, GroupBy::Week, &tz)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn count_by_tag_and_directory_sort_by_count() {
+        let (_dir, store) = temp_store(&[
+            ("2025-01-01T08:00:00+08:00", "/a", &["rust", "dev"]),
+            ("2025-01-02T08:00:00+08:00", "/b", &["rust"]),
+            ("2025-01-03T08:00:00+08:00", "/b", &[]),
+        ]);
+        let tz = east8();
+
+        let tags = store.count_by(&Query::new(), GroupBy::Tag, &tz).unwrap();
+        assert_eq!(tags, counts(&[("rust", 2), ("", 1), ("dev", 1)]));
+
+        let dirs = store
+            .count_by(&Query::new(), GroupBy::Directory, &tz)
+            .unwrap();
+        assert_eq!(dirs, counts(&[("/b", 2), ("/a", 1)]));
+        assert_eq!(store.count(&Query::new().directory("/b")).unwrap(), 2);
+    }
+}
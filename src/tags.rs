@@ -0,0 +1,291 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/tags.rs
+
+//! 关系化的标签模型 (数据库版本 3 起)。
+//!
+//! 标签存放在 `tags` 表中，通过 `log_tags` 关联表与 `logs` 建立多对多关系。
+//! 标签名中的 `/` 表示层级，例如 `programming/rust` 的父标签是 `programming`，
+//! 创建子标签时会自动创建缺失的父标签。层级关系以 `parent_id` 为准，
+//! `/` 只在创建标签时起作用，之后的重命名和移动 (`dlog tag`) 不会改写标签名。
+//!
+//! `backup` 表是归档区，仍然以逗号分隔的字符串保存标签快照，恢复时再重新关联。
+//! 重命名、合并与删除标签时，快照会在同一个事务中一并改写 ([`rewrite_backup_tags`])。
+
+use rusqlite::{params, Connection, OptionalExtension, Result};
+
+/// 在 `SELECT ... FROM logs` 中代替原 `tags` 字段的子查询，结果为逗号分隔的标签名。
+pub const LOGS_TAGS_COLUMN: &str = "(SELECT group_concat(name, ',') FROM (
+        SELECT t.name AS name FROM log_tags lt
+        JOIN tags t ON t.tag_id = lt.tag_id
+        WHERE lt.log_id = logs.id
+        ORDER BY t.name
+    )) AS tags";
+
+/// 将用户输入的 `rust, dev,rust` 解析为去重后的标签列表。
+pub fn parse_tag_list(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(',') {
+        let tag = tag.trim().trim_matches('/');
+        if !tag.is_empty() && !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+/// 查找标签的 `tag_id`，不存在时创建它 (以及所有缺失的父标签)。
+pub fn ensure_tag(conn: &Connection, name: &str) -> Result<i64> {
+    if let Some(tag_id) = find_tag(conn, name)? {
+        return Ok(tag_id);
+    }
+
+    let parent_id = match name.rsplit_once('/') {
+        Some((parent, _)) if !parent.is_empty() => Some(ensure_tag(conn, parent)?),
+        _ => None,
+    };
+
+    conn.execute(
+        "INSERT INTO tags (name, parent_id) VALUES (?1, ?2)",
+        params![name, parent_id],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// 根据名字查找标签的 `tag_id`。
+pub fn find_tag(conn: &Connection, name: &str) -> Result<Option<i64>> {
+    conn.query_row(
+        "SELECT tag_id FROM tags WHERE name = ?1",
+        [name],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// 用给定的标签列表覆盖一条日志原有的全部标签。
+pub fn set_log_tags(conn: &Connection, log_id: i64, tags: &[String]) -> Result<()> {
+    conn.execute("DELETE FROM log_tags WHERE log_id = ?1", [log_id])?;
+    for tag in tags {
+        add_log_tag(conn, log_id, tag)?;
+    }
+    Ok(())
+}
+
+/// 为一条日志追加一个标签。
+///
+/// # Returns
+/// 日志原本没有该标签时返回 true，已存在时返回 false。
+pub fn add_log_tag(conn: &Connection, log_id: i64, tag: &str) -> Result<bool> {
+    let tag_id = ensure_tag(conn, tag)?;
+    let inserted = conn.execute(
+        "INSERT OR IGNORE INTO log_tags (log_id, tag_id) VALUES (?1, ?2)",
+        params![log_id, tag_id],
+    )?;
+    Ok(inserted > 0)
+}
+
+/// 生成按标签筛选日志的 WHERE 条件，需要绑定一个参数 (标签名)。
+///
+/// `include_descendants` 为 true 时，同时匹配该标签的所有子孙标签。
+pub fn tag_filter_condition(include_descendants: bool) -> &'static str {
+    if include_descendants {
+        "id IN (
+            WITH RECURSIVE subtree(tag_id) AS (
+                SELECT tag_id FROM tags WHERE name = ?
+                UNION
+                SELECT t.tag_id FROM tags t JOIN subtree s ON t.parent_id = s.tag_id
+            )
+            SELECT log_id FROM log_tags WHERE tag_id IN subtree
+        )"
+    } else {
+        "id IN (
+            SELECT lt.log_id FROM log_tags lt
+            JOIN tags t ON t.tag_id = lt.tag_id
+            WHERE t.name = ?
+        )"
+    }
+}
+
+/// `dlog tag list` 使用的标签统计信息。
+#[derive(Debug, Clone)]
+pub struct TagInfo {
+    pub tag_id: i64,
+    pub name: String,
+    pub parent_id: Option<i64>,
+    /// 直接使用该标签的日志条数 (不含子标签)
+    pub count: i64,
+}
+
+/// 列出所有标签及其使用次数，按名字排序。
+pub fn list_tags(conn: &Connection) -> Result<Vec<TagInfo>> {
+    let mut stmt = conn.prepare(
+        "SELECT t.tag_id, t.name, t.parent_id, COUNT(lt.log_id)
+         FROM tags t
+         LEFT JOIN log_tags lt ON lt.tag_id = t.tag_id
+         GROUP BY t.tag_id
+         ORDER BY t.name",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TagInfo {
+            tag_id: row.get(0)?,
+            name: row.get(1)?,
+            parent_id: row.get(2)?,
+            count: row.get(3)?,
+        })
+    })?;
+    rows.collect()
+}
+
+/// 判断 `tag_id` 是否为 `ancestor_id` 本身或其子孙标签。
+pub fn is_descendant_of(conn: &Connection, tag_id: i64, ancestor_id: i64) -> Result<bool> {
+    conn.query_row(
+        "WITH RECURSIVE subtree(tag_id) AS (
+            SELECT ?1
+            UNION
+            SELECT t.tag_id FROM tags t JOIN subtree s ON t.parent_id = s.tag_id
+        )
+        SELECT EXISTS (SELECT 1 FROM subtree WHERE tag_id = ?2)",
+        params![ancestor_id, tag_id],
+        |row| row.get(0),
+    )
+}
+
+/// 将 `from` 标签合并到 `into` 标签：迁移所有日志关联与子标签后删除 `from`。
+///
+/// # Returns
+/// 被迁移关联的日志条数。
+pub fn merge_tag(conn: &Connection, from: i64, into: i64) -> Result<usize> {
+    let moved = conn.execute(
+        "INSERT OR IGNORE INTO log_tags (log_id, tag_id)
+         SELECT log_id, ?2 FROM log_tags WHERE tag_id = ?1",
+        params![from, into],
+    )?;
+    conn.execute(
+        "UPDATE tags SET parent_id = ?2 WHERE parent_id = ?1",
+        params![from, into],
+    )?;
+    conn.execute("DELETE FROM log_tags WHERE tag_id = ?1", [from])?;
+    conn.execute("DELETE FROM tags WHERE tag_id = ?1", [from])?;
+    Ok(moved)
+}
+
+/// 在 `backup.tags` 的快照中把标签 `old` 改为 `new`，`new` 为 None 时删除它。
+///
+/// 合并标签时快照中可能已经有 `new`，改写后会去重。
+///
+/// # Returns
+/// 被改写的备份条目数。
+pub fn rewrite_backup_tags(conn: &Connection, old: &str, new: Option<&str>) -> Result<usize> {
+    let snapshots: Vec<(i64, String)> = {
+        let mut stmt =
+            conn.prepare("SELECT id, tags FROM backup WHERE tags IS NOT NULL AND tags != ''")?;
+        let rows = stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+        rows.collect::<Result<_>>()?
+    };
+
+    let mut rewritten = 0;
+    for (id, snapshot) in snapshots {
+        let tags = parse_tag_list(&snapshot);
+        if !tags.iter().any(|tag| tag == old) {
+            continue;
+        }
+        let renamed: Vec<&str> = tags
+            .iter()
+            .filter_map(|tag| if tag == old { new } else { Some(tag.as_str()) })
+            .collect();
+        let tags = parse_tag_list(&renamed.join(","));
+        conn.execute(
+            "UPDATE backup SET tags = ?1 WHERE id = ?2",
+            params![tags.join(","), id],
+        )?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
+
+/// 删除一个标签并解除它与所有日志的关联，其子标签会挂到它的父标签下。
+///
+/// # Returns
+/// 受影响的日志条数。
+pub fn delete_tag(conn: &Connection, tag_id: i64) -> Result<usize> {
+    conn.execute(
+        "UPDATE tags SET parent_id = (SELECT parent_id FROM tags WHERE tag_id = ?1)
+         WHERE parent_id = ?1",
+        [tag_id],
+    )?;
+    let unlinked = conn.execute("DELETE FROM log_tags WHERE tag_id = ?1", [tag_id])?;
+    conn.execute("DELETE FROM tags WHERE tag_id = ?1", [tag_id])?;
+    Ok(unlinked)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db;
+    use crate::testutil::{temp_db_path, temp_store};
+
+    /// 按标签筛选 logs，返回匹配的日志 id
+    fn matching(conn: &Connection, tag: &str, include_descendants: bool) -> Vec<i64> {
+        let sql = format!(
+            "SELECT id FROM logs WHERE {} ORDER BY id",
+            tag_filter_condition(include_descendants)
+        );
+        let mut stmt = conn.prepare(&sql).unwrap();
+        let rows = stmt.query_map([tag], |row| row.get(0)).unwrap();
+        rows.collect::<Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn parse_tag_list_trims_and_dedupes() {
+        assert_eq!(
+            parse_tag_list(" rust, dev,rust,, /programming/rust/ "),
+            vec!["rust", "dev", "programming/rust"]
+        );
+    }
+
+    #[test]
+    fn ensure_tag_creates_missing_parents() {
+        let (_dir, path) = temp_db_path();
+        db::initialize_db(&path).unwrap();
+        let conn = Connection::open(&path).unwrap();
+
+        let leaf = ensure_tag(&conn, "a/b/c").unwrap();
+        let a = find_tag(&conn, "a").unwrap().unwrap();
+        let b = find_tag(&conn, "a/b").unwrap().unwrap();
+        assert!(is_descendant_of(&conn, leaf, a).unwrap());
+        assert!(is_descendant_of(&conn, leaf, b).unwrap());
+        assert!(!is_descendant_of(&conn, a, leaf).unwrap());
+        assert_eq!(ensure_tag(&conn, "a/b").unwrap(), b);
+    }
+
+    #[test]
+    fn tag_filter_condition_matches_subtags() {
+        let (_dir, store) = temp_store(&[
+            ("2025-01-01T00:00:00Z", "/", &["programming"]),
+            ("2025-01-02T00:00:00Z", "/", &["programming/rust"]),
+            ("2025-01-03T00:00:00Z", "/", &["programming/rust/async"]),
+            ("2025-01-04T00:00:00Z", "/", &["programming-notes"]),
+            ("2025-01-05T00:00:00Z", "/", &["rust"]),
+        ]);
+        let conn = Connection::open(store.path()).unwrap();
+
+        assert_eq!(matching(&conn, "programming", false), vec![1]);
+        // 子孙标签按 parent_id 匹配，名字前缀相同的 `programming-notes` 不算
+        assert_eq!(matching(&conn, "programming", true), vec![1, 2, 3]);
+        assert_eq!(matching(&conn, "programming/rust", true), vec![2, 3]);
+        // 同名的顶层标签与子标签互不相干
+        assert_eq!(matching(&conn, "rust", true), vec![5]);
+        assert!(matching(&conn, "missing", true).is_empty());
+    }
+}
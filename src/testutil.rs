@@ -0,0 +1,61 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/ident.rs
+// src/testutil.rs
+
+//! 单元测试共用的辅助函数。
+
+use crate::store::{NewLog, Store};
+use crate::timeparse::UserTimeZone;
+use chrono::FixedOffset;
+use rusqlite::{params, Connection};
+use std::path::PathBuf;
+use tempfile::TempDir;
+
+/// 东八区。测试使用固定的时区，结果不受运行环境影响
+pub fn east8() -> UserTimeZone {
+    UserTimeZone::Fixed(FixedOffset::east_opt(8 * 3600).unwrap())
+}
+
+/// 临时目录中的数据库路径，数据库文件尚未创建。目录在 `TempDir` 被丢弃时删除
+pub fn temp_db_path() -> (TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("dlog.db");
+    (dir, path)
+}
+
+/// 在临时目录中创建数据库，按 `(时间戳, 目录, 标签)` 写入日志，时间戳为 RFC 3339 格式。
+///
+/// 日志内容为 `written at <时间戳>`。
+pub fn temp_store(logs: &[(&str, &str, &[&str])]) -> (TempDir, Store) {
+    let (dir, path) = temp_db_path();
+    let mut store = Store::init(&path).unwrap();
+    let conn = Connection::open(&path).unwrap();
+    for (timestamp, directory, tags) in logs {
+        let log = store
+            .insert(&NewLog {
+                directory: directory.to_string(),
+                content: format!("written at {}", timestamp),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                ..Default::default()
+            })
+            .unwrap();
+        conn.execute(
+            "UPDATE logs SET timestamp = ?1 WHERE id = ?2",
+            params![timestamp, log.id],
+        )
+        .unwrap();
+    }
+    (dir, store)
+}
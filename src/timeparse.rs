@@ -0,0 +1,456 @@
+// Copyright 2025 ABdeeglr Ramsay
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+// src/timeparse.rs
+
+//! 解析 `--since` / `--until` 等参数中用户书写的时间表达式。
+//!
+//! 支持的写法：
+//! - 绝对时间：`2025-10-01`、`2025-10-01 14:30`、`2025-10-01 14:30:00`
+//! - 相对时长：`30m`、`3h`、`2d`、`1w` (从现在往前推)
+//! - 自然语言：`now`、`today`、`yesterday`、`monday`、`last monday`
+//!
+//! 日期与墙上时间按 [`UserTimeZone`] 解释，再转换为 UTC 区间与数据库中的时间戳比较。
+
+use anyhow::{bail, Context, Result};
+use chrono::{
+    DateTime, Datelike, Days, FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, TimeDelta,
+    TimeZone, Utc, Weekday,
+};
+use chrono_tz::Tz;
+
+/// 用户书写和阅读时间时所使用的时区。
+///
+/// 默认为系统本地时区，可以通过环境变量 `DLOG_TZ` 指定一个固定的 UTC 偏移，
+/// 例如 `DLOG_TZ=+08:00`、`DLOG_TZ=-0530` 或 `DLOG_TZ=UTC`，
+/// 或者一个 IANA 时区名，例如 `DLOG_TZ=America/New_York` (会跟随夏令时调整偏移)。
+#[derive(Debug, Clone, Copy)]
+pub enum UserTimeZone {
+    Local,
+    Fixed(FixedOffset),
+    Named(Tz),
+}
+
+impl UserTimeZone {
+    /// 读取 `DLOG_TZ` 环境变量，未设置或为 `local` 时使用系统本地时区。
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("DLOG_TZ") {
+            Ok(value) => {
+                Self::parse(&value).with_context(|| format!("DLOG_TZ 的值 '{}' 无效", value))
+            }
+            Err(_) => Ok(UserTimeZone::Local),
+        }
+    }
+
+    fn parse(value: &str) -> Result<Self> {
+        let value = value.trim();
+        if value.is_empty() || value.eq_ignore_ascii_case("local") {
+            return Ok(UserTimeZone::Local);
+        }
+        if value.eq_ignore_ascii_case("utc") || value.eq_ignore_ascii_case("z") {
+            return Ok(UserTimeZone::Fixed(FixedOffset::east_opt(0).expect("zero offset")));
+        }
+
+        // 接受 +08:00、+0800、+08 三种写法，其他的按 IANA 时区名解析
+        let (sign, digits) = if let Some(rest) = value.strip_prefix('+') {
+            (1, rest)
+        } else if let Some(rest) = value.strip_prefix('-') {
+            (-1, rest)
+        } else {
+            return match value.parse::<Tz>() {
+                Ok(tz) => Ok(UserTimeZone::Named(tz)),
+                Err(_) => bail!("时区需要是以 + 或 - 开头的偏移，或者 Asia/Shanghai 这样的时区名"),
+            };
+        };
+        let digits: String = digits.chars().filter(|c| *c != ':').collect();
+        if !digits.chars().all(|c| c.is_ascii_digit()) {
+            bail!("时区偏移的格式应为 +HH:MM");
+        }
+        let (hours, minutes) = match digits.len() {
+            2 => (digits.parse::<i32>()?, 0),
+            4 => (digits[..2].parse::<i32>()?, digits[2..].parse::<i32>()?),
+            _ => bail!("时区偏移的格式应为 +HH:MM"),
+        };
+        FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+            .map(UserTimeZone::Fixed)
+            .context("时区偏移超出范围")
+    }
+
+    /// 该时区中的当前时间。
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        self.convert(Utc::now())
+    }
+
+    /// 将 UTC 时间转换到该时区。
+    pub fn convert(&self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            UserTimeZone::Local => time.with_timezone(&Local).fixed_offset(),
+            UserTimeZone::Fixed(offset) => time.with_timezone(offset),
+            UserTimeZone::Named(tz) => time.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// 将数据库中保存的 RFC 3339 时间显示为该时区的 `YYYY-MM-DD HH:MM:SS`，无法解析时原样返回。
+    pub fn display_rfc3339(&self, time: &str) -> String {
+        match DateTime::parse_from_rfc3339(time) {
+            Ok(time) => self
+                .convert(time.with_timezone(&Utc))
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string(),
+            Err(_) => time.to_string(),
+        }
+    }
+
+    /// 将该时区中的墙上时间转换为带偏移的时间，夏令时重叠时取较早的一个。
+    pub fn resolve(&self, naive: NaiveDateTime) -> Result<DateTime<FixedOffset>> {
+        let resolved = match self {
+            UserTimeZone::Local => Local.from_local_datetime(&naive).map(|t| t.fixed_offset()),
+            UserTimeZone::Fixed(offset) => offset.from_local_datetime(&naive),
+            UserTimeZone::Named(tz) => tz.from_local_datetime(&naive).map(|t| t.fixed_offset()),
+        };
+        match resolved {
+            LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => Ok(t),
+            LocalResult::None => bail!("当前时区中不存在时间 {}", naive),
+        }
+    }
+
+    /// 某一天在该时区中覆盖的 UTC 区间 `[当天零点, 次日零点)`。
+    pub fn day_bounds(&self, date: NaiveDate) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+        let midnight =
+            |d: NaiveDate| self.resolve(d.and_hms_opt(0, 0, 0).expect("midnight is always valid"));
+        Ok((
+            midnight(date)?.with_timezone(&Utc),
+            midnight(date.succ_opt().context("日期超出可表示的范围")?)?.with_timezone(&Utc),
+        ))
+    }
+}
+
+/// 解析后的时间表达式，表示它所覆盖的 UTC 区间。
+///
+/// 精确到时刻的表达式 (如 `3h`) 起止相同；只精确到天的表达式 (如 `yesterday`)
+/// 覆盖当天全天。
+#[derive(Debug, Clone, Copy)]
+pub struct TimeSpec {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl TimeSpec {
+    fn instant(at: DateTime<FixedOffset>) -> Self {
+        let at = at.with_timezone(&Utc);
+        TimeSpec { start: at, end: at }
+    }
+
+    fn day(tz: &UserTimeZone, date: NaiveDate) -> Result<Self> {
+        let (start, end) = tz.day_bounds(date)?;
+        Ok(TimeSpec { start, end })
+    }
+
+    /// 作为区间起点使用：取覆盖区间的开始。
+    pub fn as_lower_bound(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// 作为区间终点使用 (不含)：精度为天时包含当天全天。
+    pub fn as_upper_bound(&self) -> DateTime<Utc> {
+        self.end
+    }
+}
+
+/// 将用户输入的时间表达式解析为 [`TimeSpec`]，绝对时间按 `tz` 解释。
+pub fn parse_time_spec(input: &str, tz: &UserTimeZone) -> Result<TimeSpec> {
+    let now = tz.now();
+    let text = input.trim().to_lowercase();
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    // 1. 绝对时间
+    for format in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"] {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(&text, format) {
+            return Ok(TimeSpec::instant(tz.resolve(naive)?));
+        }
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+        return TimeSpec::day(tz, date);
+    }
+
+    // 2. 自然语言
+    match text.as_str() {
+        "now" => return Ok(TimeSpec::instant(now)),
+        "today" => return TimeSpec::day(tz, now.date_naive()),
+        "yesterday" => {
+            let yesterday = now
+                .date_naive()
+                .pred_opt()
+                .context("日期超出可表示的范围")?;
+            return TimeSpec::day(tz, yesterday);
+        }
+        _ => {}
+    }
+    let weekday_text = text.strip_prefix("last ").unwrap_or(&text);
+    if let Ok(weekday) = weekday_text.parse::<Weekday>() {
+        // 取今天之前最近的一个该星期几
+        let today = now.date_naive();
+        let mut days_back = (7 + today.weekday().num_days_from_monday()
+            - weekday.num_days_from_monday())
+            % 7;
+        if days_back == 0 {
+            days_back = 7;
+        }
+        let day = today
+            .checked_sub_days(Days::new(days_back.into()))
+            .context("日期超出可表示的范围")?;
+        return TimeSpec::day(tz, day);
+    }
+
+    // 3. 相对时长
+    if let Some(duration) = parse_duration(&text)? {
+        let at = now
+            .checked_sub_signed(duration)
+            .with_context(|| format!("时间 '{}' 超出可表示的范围", input))?;
+        return Ok(TimeSpec::instant(at));
+    }
+
+    bail!(
+        "无法解析时间 '{}'，支持的格式: YYYY-MM-DD, 'YYYY-MM-DD HH:MM', 30m/3h/2d/1w, today, yesterday, last monday",
+        input
+    )
+}
+
+/// 解析用户输入的 `YYYY-MM-DD` 日期。
+pub fn parse_date(input: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d")
+        .with_context(|| format!("无法解析日期 '{}'，格式应为 YYYY-MM-DD", input))
+}
+
+/// 解析待办事项的截止日期。
+///
+/// 除 `YYYY-MM-DD` 外，还支持 `today`、`tomorrow`、下一个星期几 (`friday`)
+/// 以及从今天往后推的 `3d`、`2w`。
+pub fn parse_due_date(input: &str, tz: &UserTimeZone) -> Result<NaiveDate> {
+    let text = input.trim().to_lowercase();
+    let today = tz.now().date_naive();
+
+    if let Ok(date) = NaiveDate::parse_from_str(&text, "%Y-%m-%d") {
+        return Ok(date);
+    }
+    match text.as_str() {
+        "today" => return Ok(today),
+        "tomorrow" => return today.succ_opt().context("日期超出可表示的范围"),
+        _ => {}
+    }
+    let weekday_text = text.strip_prefix("next ").unwrap_or(&text);
+    if let Ok(weekday) = weekday_text.parse::<Weekday>() {
+        // 取今天之后最近的一个该星期几
+        let mut days_ahead = (7 + weekday.num_days_from_monday()
+            - today.weekday().num_days_from_monday())
+            % 7;
+        if days_ahead == 0 {
+            days_ahead = 7;
+        }
+        return today
+            .checked_add_days(Days::new(days_ahead.into()))
+            .context("日期超出可表示的范围");
+    }
+    if let Some(duration) = parse_duration(&text)? {
+        return u64::try_from(duration.num_days())
+            .ok()
+            .and_then(|days| today.checked_add_days(Days::new(days)))
+            .with_context(|| format!("截止日期 '{}' 超出可表示的范围", input));
+    }
+
+    bail!(
+        "无法解析截止日期 '{}'，支持的格式: YYYY-MM-DD, today, tomorrow, friday, 3d, 2w",
+        input
+    )
+}
+
+/// 解析 `30m`、`3h`、`2d`、`1w` 形式的时长。
+///
+/// 不是这种形式时返回 `Ok(None)`，数值超出可表示的范围时返回错误。
+fn parse_duration(text: &str) -> Result<Option<TimeDelta>> {
+    let Some(unit_pos) = text.find(|c: char| !c.is_ascii_digit()) else {
+        return Ok(None);
+    };
+    let (number, unit) = text.split_at(unit_pos);
+    if number.is_empty() {
+        return Ok(None);
+    }
+    let constructor: fn(i64) -> Option<TimeDelta> = match unit {
+        "m" | "min" | "mins" | "minute" | "minutes" => TimeDelta::try_minutes,
+        "h" | "hour" | "hours" => TimeDelta::try_hours,
+        "d" | "day" | "days" => TimeDelta::try_days,
+        "w" | "week" | "weeks" => TimeDelta::try_weeks,
+        _ => return Ok(None),
+    };
+    number
+        .parse()
+        .ok()
+        .and_then(constructor)
+        .map(Some)
+        .with_context(|| format!("时长 '{}' 超出可表示的范围", text))
+}
+
+/// 将 UTC 时间格式化为 SQLite `datetime()` 的输出格式，便于在 SQL 中比较。
+pub fn to_sql_datetime(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::east8;
+
+    /// 相对时长以调用时刻为准，允许测试运行期间的少量误差
+    fn assert_about(actual: DateTime<Utc>, expected: DateTime<Utc>) {
+        let diff = (actual - expected).num_seconds().abs();
+        assert!(diff <= 5, "{} 与 {} 相差 {} 秒", actual, expected, diff);
+    }
+
+    #[test]
+    fn relative_durations_count_back_from_now() {
+        let tz = east8();
+        for (text, expected) in [
+            ("30m", TimeDelta::minutes(30)),
+            ("3h", TimeDelta::hours(3)),
+            ("3d", TimeDelta::days(3)),
+            ("2w", TimeDelta::weeks(2)),
+        ] {
+            let spec = parse_time_spec(text, &tz).unwrap();
+            assert_eq!(spec.start, spec.end, "{}", text);
+            assert_about(spec.start, Utc::now() - expected);
+        }
+    }
+
+    #[test]
+    fn weekday_names_cover_the_previous_such_day() {
+        let tz = east8();
+        let today = tz.now().date_naive();
+        for name in ["monday", "tue", "last wednesday", "Friday", "last sunday"] {
+            let spec = parse_time_spec(name, &tz).unwrap();
+            assert_eq!(spec.end - spec.start, TimeDelta::days(1), "{}", name);
+
+            let day = tz.convert(spec.start).date_naive();
+            let weekday = name.trim_start_matches("last ").parse::<Weekday>().unwrap();
+            assert_eq!(day.weekday(), weekday, "{}", name);
+            // 总是今天之前的 1 到 7 天，不包括今天
+            let days_back = (today - day).num_days();
+            assert!((1..=7).contains(&days_back), "{}: {} 天前", name, days_back);
+        }
+    }
+
+    #[test]
+    fn day_names_use_the_user_time_zone() {
+        let tz = east8();
+        let spec = parse_time_spec("2025-10-01", &tz).unwrap();
+        assert_eq!(spec.start.to_rfc3339(), "2025-09-30T16:00:00+00:00");
+        assert_eq!(spec.end.to_rfc3339(), "2025-10-01T16:00:00+00:00");
+
+        let yesterday = parse_time_spec("yesterday", &tz).unwrap();
+        let today = parse_time_spec("today", &tz).unwrap();
+        assert_eq!(yesterday.end, today.start);
+    }
+
+    #[test]
+    fn out_of_range_input_is_an_error_not_a_panic() {
+        let tz = east8();
+        for text in ["100000000d", "4000000000w", "99999999999999999999m"] {
+            assert!(parse_time_spec(text, &tz).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn due_dates_count_forward_from_today() {
+        let tz = east8();
+        let today = tz.now().date_naive();
+        let due = |text: &str| parse_due_date(text, &tz).unwrap();
+        assert_eq!(due("today"), today);
+        assert_eq!(due("tomorrow"), today + Days::new(1));
+        assert_eq!(due("3d"), today + Days::new(3));
+        assert_eq!(due("2w"), today + Days::new(14));
+        assert_eq!(
+            due("2025-12-31"),
+            NaiveDate::from_ymd_opt(2025, 12, 31).unwrap()
+        );
+
+        // 星期几总是今天之后的 1 到 7 天
+        let friday = due("next friday");
+        assert_eq!(friday.weekday(), Weekday::Fri);
+        assert!((1..=7).contains(&(friday - today).num_days()));
+
+        for text in ["99999999999d", "4000000000w", "99999999999999999999d"] {
+            assert!(parse_due_date(text, &tz).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn unknown_input_is_rejected() {
+        let tz = east8();
+        for text in ["", "d", "3x", "next century"] {
+            assert!(parse_time_spec(text, &tz).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn dlog_tz_accepts_offsets_and_zone_names() {
+        let offset = |value: &str| match UserTimeZone::parse(value).unwrap() {
+            UserTimeZone::Fixed(offset) => offset.local_minus_utc(),
+            other => panic!("{}: {:?}", value, other),
+        };
+        assert_eq!(offset("+08:00"), 8 * 3600);
+        assert_eq!(offset("-0530"), -(5 * 3600 + 30 * 60));
+        assert_eq!(offset("UTC"), 0);
+        assert!(matches!(
+            UserTimeZone::parse("local"),
+            Ok(UserTimeZone::Local)
+        ));
+        assert!(matches!(
+            UserTimeZone::parse("America/New_York"),
+            Ok(UserTimeZone::Named(Tz::America__New_York))
+        ));
+        for value in ["+8:00:00", "+25:00", "Mars/Olympus"] {
+            assert!(UserTimeZone::parse(value).is_err(), "{}", value);
+        }
+    }
+
+    /// 夏令时切换的日子长 23 或 25 小时，区间按当地的零点计算
+    #[test]
+    fn day_bounds_follow_dst_transitions() {
+        let tz = UserTimeZone::Named(Tz::America__New_York);
+        let hours = |date: &str| {
+            let (start, end) = tz.day_bounds(parse_date(date).unwrap()).unwrap();
+            (end - start).num_hours()
+        };
+        assert_eq!(hours("2026-03-08"), 23);
+        assert_eq!(hours("2026-06-01"), 24);
+        assert_eq!(hours("2026-11-01"), 25);
+
+        let (start, _) = tz.day_bounds(parse_date("2026-06-01").unwrap()).unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-06-01T04:00:00+00:00");
+        let (start, _) = tz.day_bounds(parse_date("2026-01-15").unwrap()).unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-01-15T05:00:00+00:00");
+
+        // 夏令时开始时跳过的墙上时间不存在
+        let skipped = NaiveDate::from_ymd_opt(2026, 3, 8)
+            .unwrap()
+            .and_hms_opt(2, 30, 0)
+            .unwrap();
+        assert!(tz.resolve(skipped).is_err());
+    }
+
+    #[test]
+    fn last_representable_day_is_an_error_not_a_panic() {
+        let tz = east8();
+        assert!(tz.day_bounds(NaiveDate::MAX).is_err());
+        assert!(parse_time_spec("+262143-12-31", &tz).is_err());
+    }
+}